@@ -1,6 +1,7 @@
 //! App component.
 
-use super::{FileSelect, Stats};
+use super::{FileSelect, ScanSettings, Stats};
+use crate::services::*;
 use dioxus::prelude::*;
 use std::path::PathBuf;
 
@@ -12,6 +13,8 @@ enum AppState {
     Home,
     /// The directory selection page.
     DirectorySelection,
+    /// The scan settings page, shown for the selected directory.
+    ScanSettings(PathBuf),
     /// The code statistics page.
     DisplayingStats(PathBuf),
 }
@@ -19,6 +22,7 @@ enum AppState {
 /// The top-level app component.
 pub fn App(cx: Scope) -> Element {
     let app_state = use_state(cx, AppState::default);
+    let scan_options = use_state(cx, ScanOptions::default);
 
     render! {
         div {
@@ -60,15 +64,32 @@ pub fn App(cx: Scope) -> Element {
                         FileSelect {
                             directory: true,
                             cancelable: true,
-                            on_select: move |path| app_state.set(AppState::DisplayingStats(path)),
+                            on_select: move |path| app_state.set(AppState::ScanSettings(path)),
                             on_cancel: move |_| app_state.set(AppState::Home)
                         }
                     }
                 },
+                AppState::ScanSettings(path) => render! {
+                    div {
+                        class: "path-select",
+
+                        ScanSettings {
+                            path: path,
+                            options: scan_options,
+                            on_start: move |options| {
+                                scan_options.set(options);
+                                app_state.set(AppState::DisplayingStats(path.clone()));
+                            },
+                            on_cancel: move |_| app_state.set(AppState::DirectorySelection)
+                        }
+                    }
+                },
                 AppState::DisplayingStats(path) => render! {
                     Stats {
                         path: path,
-                        on_done: move |_| app_state.set(AppState::Home)
+                        options: scan_options,
+                        on_done: move |_| app_state.set(AppState::Home),
+                        on_choose_directory: move |_| app_state.set(AppState::DirectorySelection)
                     }
                 }
             }
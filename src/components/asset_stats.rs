@@ -0,0 +1,72 @@
+//! Breakdown of binary files by media type.
+
+use crate::services::*;
+use dioxus::prelude::*;
+
+/// Asset statistics properties.
+#[derive(Props)]
+pub struct AssetStatsProps<'a> {
+    /// The statistics.
+    stats: &'a DirStats,
+    /// The filter choosing which files are included.
+    filter: StatsFilter,
+}
+
+/// Asset statistics component. Lists binary files, which are excluded from the
+/// language statistics, grouped by media type.
+pub fn AssetStats<'a>(cx: Scope<'a, AssetStatsProps<'a>>) -> Element {
+    let tallies = cx.props.stats.tallies(&cx.props.filter);
+
+    if tallies.assets.is_empty() {
+        return None;
+    }
+
+    let total_files = tallies
+        .assets
+        .values()
+        .map(|counts| counts.files)
+        .sum::<usize>();
+    let total_bytes = tallies
+        .assets
+        .values()
+        .map(|counts| counts.bytes)
+        .sum::<usize>();
+    let summary = format!(
+        "Assets: {} binary files, {} bytes",
+        format_with_commas(total_files),
+        format_with_commas(total_bytes)
+    );
+
+    render! {
+        details {
+            class: "breakdown-stats",
+
+            summary {
+                class: "breakdown-stats-summary",
+                summary
+            }
+
+            table {
+                class: "breakdown-stats-table",
+
+                thead {
+                    tr {
+                        th { "Type" }
+                        th { "Files" }
+                        th { "Bytes" }
+                    }
+                }
+
+                tbody {
+                    for (media_type, counts) in tallies.assets.iter() {
+                        tr {
+                            td { media_type.name() }
+                            td { format_with_commas(counts.files) }
+                            td { format_with_commas(counts.bytes) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
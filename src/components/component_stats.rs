@@ -0,0 +1,66 @@
+//! Breakdown of files by project component.
+
+use crate::services::*;
+use dioxus::prelude::*;
+use std::cmp::Reverse;
+
+/// Component statistics properties.
+#[derive(Props)]
+pub struct ComponentStatsProps<'a> {
+    /// The statistics.
+    stats: &'a DirStats,
+    /// The filter choosing which files are included.
+    filter: StatsFilter,
+}
+
+/// Component statistics component. Lists the components configured in the
+/// project's configuration files, with the totals of the text files belonging
+/// to each.
+pub fn ComponentStats<'a>(cx: Scope<'a, ComponentStatsProps<'a>>) -> Element {
+    let tallies = cx.props.stats.tallies(&cx.props.filter);
+
+    if tallies.components.is_empty() {
+        return None;
+    }
+
+    let mut ordered_stats = tallies.components.iter().collect::<Vec<_>>();
+    ordered_stats.sort_by_key(|(_, counts)| Reverse(counts.lines));
+    let summary = format!("Components: {}", format_with_commas(ordered_stats.len()));
+
+    render! {
+        details {
+            class: "breakdown-stats",
+
+            summary {
+                class: "breakdown-stats-summary",
+                summary
+            }
+
+            table {
+                class: "breakdown-stats-table",
+
+                thead {
+                    tr {
+                        th { "Component" }
+                        th { "Files" }
+                        th { "Lines" }
+                        th { "Code lines" }
+                        th { "Bytes" }
+                    }
+                }
+
+                tbody {
+                    for (component, counts) in ordered_stats {
+                        tr {
+                            td { component.as_str() }
+                            td { format_with_commas(counts.files) }
+                            td { format_with_commas(counts.lines) }
+                            td { format_with_commas(counts.code) }
+                            td { format_with_commas(counts.bytes) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -4,7 +4,7 @@ use super::{Icon, Loading, PathDisplay};
 use crate::icons::*;
 use crate::services::*;
 use dioxus::prelude::*;
-use std::io;
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf, MAIN_SEPARATOR, MAIN_SEPARATOR_STR};
 
 /// The current state of the directory structure.
@@ -14,7 +14,7 @@ enum DirectoryInfoState {
     /// Done fetching directory information.
     Completed(DirectoryInfo),
     /// An error occurred while fetching directory information.
-    Error(io::Error),
+    Error(ServiceError),
 }
 
 /// File selection properties.
@@ -42,6 +42,7 @@ pub fn FileSelect<'a>(cx: Scope<'a, FileSelectProps<'a>>) -> Element {
     let selection = use_state(cx, || None);
     let selecting_directory = use_state(cx, || None::<PathBuf>);
     let status = use_state(cx, || DirectoryInfoState::Fetching);
+    let fetch_count = use_state(cx, || 0usize);
 
     use_on_create(cx, {
         to_owned![current_path];
@@ -52,13 +53,13 @@ pub fn FileSelect<'a>(cx: Scope<'a, FileSelectProps<'a>>) -> Element {
         }
     });
 
-    let set_selection = |entry: &str, directory: bool| {
+    let set_selection = |entry: &OsStr, directory: bool| {
         let path = PathBuf::from(entry);
 
         if let DirectoryInfoState::Completed(_) = &**status {
             if cx.props.directory && directory {
                 match &**selecting_directory {
-                    Some(path) if path.to_str() == Some(entry) => {
+                    Some(path) if path.as_os_str() == entry => {
                         let path = current_path.join(entry);
                         current_path.set(path.clone());
                         selection.set(Some(path));
@@ -79,7 +80,7 @@ pub fn FileSelect<'a>(cx: Scope<'a, FileSelectProps<'a>>) -> Element {
         }
     };
 
-    use_future(cx, (current_path,), |(current_path,)| {
+    use_future(cx, (current_path, fetch_count), |(current_path, _)| {
         to_owned![status];
         async move {
             status.set(DirectoryInfoState::Fetching);
@@ -129,7 +130,7 @@ pub fn FileSelect<'a>(cx: Scope<'a, FileSelectProps<'a>>) -> Element {
                     button {
                         r#type: "button",
                         class: "icon-button",
-                        disabled: **current_path == PathBuf::from("") || **current_path == PathBuf::from("/"),
+                        disabled: **current_path == Path::new("") || **current_path == Path::new("/"),
                         onclick: move |_| {
                             if let Some(path) = current_path.parent() {
                                 current_path.set(path.to_path_buf());
@@ -166,24 +167,25 @@ pub fn FileSelect<'a>(cx: Scope<'a, FileSelectProps<'a>>) -> Element {
                                     let mut classes = vec!["dir-info-dir"];
 
                                     if let Some(path) = &**selection {
-                                        if path.to_str() == Some(entry) {
+                                        if path.as_os_str() == entry {
                                             classes.push("dir-info-selected");
                                         }
                                     }
 
                                     if let Some(path) = &**selecting_directory {
-                                        if path.to_str() == Some(entry) {
+                                        if path.as_os_str() == entry {
                                             classes.push("dir-info-selecting-directory");
                                         }
                                     }
 
                                     let classes = classes.join(" ");
-                                    let entry_str = entry.strip_suffix(MAIN_SEPARATOR).unwrap_or(entry);
+                                    let entry_lossy = entry.to_string_lossy();
+                                    let entry_str = entry_lossy.strip_suffix(MAIN_SEPARATOR).unwrap_or(&entry_lossy).to_owned();
 
                                     render! {
                                         div {
                                             class: "{classes}",
-                                            onclick: move |_| set_selection(entry.as_str(), true),
+                                            onclick: move |_| set_selection(entry, true),
 
                                             Icon {
                                                 data: FOLDER,
@@ -200,24 +202,25 @@ pub fn FileSelect<'a>(cx: Scope<'a, FileSelectProps<'a>>) -> Element {
                                     let mut classes = vec!["dir-info-file"];
 
                                     if let Some(path) = &**selection {
-                                        if path.to_str() == Some(entry) {
+                                        if path.as_os_str() == entry {
                                             classes.push("dir-info-selected");
                                         }
                                     }
 
                                     let classes = classes.join(" ");
+                                    let entry_str = entry.to_string_lossy();
 
                                     render! {
                                         div {
                                             class: "{classes}",
-                                            onclick: move |_| set_selection(entry.as_str(), false),
+                                            onclick: move |_| set_selection(entry, false),
 
                                             Icon {
                                                 data: FILE,
                                                 class: "file-icon"
                                             }
                                             span {
-                                                entry.as_str()
+                                                "{entry_str}"
                                             }
                                         }
                                     }
@@ -234,9 +237,36 @@ pub fn FileSelect<'a>(cx: Scope<'a, FileSelectProps<'a>>) -> Element {
                     },
                     DirectoryInfoState::Error(err) => render! {
                         div {
-                            class: "error dir-info-error",
-                            "An error occurred while fetching directory info: "
-                            {err.to_string()}
+                            class: "dir-info-error",
+
+                            div {
+                                class: "error",
+                                "This directory could not be opened"
+                            }
+                            div {
+                                class: "dir-info-error-details",
+                                err.to_string()
+                            }
+                            div {
+                                class: "dir-info-error-actions",
+
+                                button {
+                                    r#type: "button",
+                                    class: "button secondary",
+                                    disabled: **current_path == Path::new("") || **current_path == Path::new("/"),
+                                    onclick: move |_| {
+                                        current_path.set(current_path.parent().unwrap_or(Path::new("")).to_path_buf());
+                                        selection.set(None);
+                                    },
+                                    "Go up"
+                                }
+                                button {
+                                    r#type: "button",
+                                    class: "button primary",
+                                    onclick: move |_| fetch_count.modify(|count| count + 1),
+                                    "Retry"
+                                }
+                            }
                         }
                     },
                 }
@@ -251,10 +281,8 @@ pub fn FileSelect<'a>(cx: Scope<'a, FileSelectProps<'a>>) -> Element {
                     match &**selection {
                         Some(path) => {
                             match path.iter()
-                                .filter_map(|component|
-                                    component.to_str()
-                                        .filter(|&component| component != MAIN_SEPARATOR_STR))
-                                .last()
+                                .map(|component| component.to_string_lossy())
+                                .rfind(|component| component != MAIN_SEPARATOR_STR)
                             {
                                 Some(name) => render! {
                                     div {
@@ -0,0 +1,97 @@
+//! Selection of the files included in statistics.
+
+use crate::services::*;
+use dioxus::prelude::*;
+
+/// Filter selection properties.
+#[derive(Props)]
+pub struct FilterSelectProps<'a> {
+    /// The current filter.
+    filter: StatsFilter,
+    /// The callback to signal that the filter has changed.
+    on_change: EventHandler<'a, StatsFilter>,
+}
+
+/// A toggleable filter option.
+#[derive(Props)]
+struct FilterOptionProps<'a> {
+    /// The label of the option.
+    label: &'a str,
+    /// Whether the option is selected.
+    selected: bool,
+    /// The callback to signal that the option has been toggled.
+    on_toggle: EventHandler<'a, bool>,
+}
+
+/// A toggleable filter option component.
+fn FilterOption<'a>(cx: Scope<'a, FilterOptionProps<'a>>) -> Element {
+    render! {
+        button {
+            r#type: "button",
+            class: if cx.props.selected {
+                "filter-select-option filter-select-option-selected"
+            } else {
+                "filter-select-option"
+            },
+            onclick: move |_| cx.props.on_toggle.call(!cx.props.selected),
+            cx.props.label
+        }
+    }
+}
+
+/// Filter selection component, choosing the language categories and tagged
+/// files that are included in statistics.
+pub fn FilterSelect<'a>(cx: Scope<'a, FilterSelectProps<'a>>) -> Element {
+    let filter = cx.props.filter;
+
+    render! {
+        div {
+            class: "filter-select",
+
+            span {
+                class: "filter-select-label",
+                "Include:"
+            }
+
+            for category in LanguageCategory::ALL {
+                FilterOption {
+                    label: category.name(),
+                    selected: filter.categories.contains(category),
+                    on_toggle: move |selected| cx.props.on_change.call(StatsFilter {
+                        categories: filter.categories.with(category, selected),
+                        ..filter
+                    })
+                }
+            }
+
+            span {
+                class: "filter-select-separator"
+            }
+
+            FilterOption {
+                label: "generated",
+                selected: filter.generated,
+                on_toggle: move |generated| cx.props.on_change.call(StatsFilter {
+                    generated,
+                    ..filter
+                })
+            }
+            FilterOption {
+                label: "vendored",
+                selected: filter.vendored,
+                on_toggle: move |vendored| cx.props.on_change.call(StatsFilter {
+                    vendored,
+                    ..filter
+                })
+            }
+            FilterOption {
+                label: "documentation",
+                selected: filter.documentation,
+                on_toggle: move |documentation| cx.props.on_change.call(StatsFilter {
+                    documentation,
+                    ..filter
+                })
+            }
+        }
+    }
+}
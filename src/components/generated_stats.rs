@@ -0,0 +1,102 @@
+//! Breakdown of generated files by language.
+
+use crate::services::*;
+use dioxus::prelude::*;
+use std::cmp::Reverse;
+
+/// Generated file statistics properties.
+#[derive(Props)]
+pub struct GeneratedStatsProps<'a> {
+    /// The statistics.
+    stats: &'a DirStats,
+    /// The filter choosing which files are included.
+    filter: StatsFilter,
+}
+
+/// Generated file statistics component. Shows how much of each language is
+/// generated, minified or lock files, whether or not those files are currently
+/// included in the statistics.
+pub fn GeneratedStats<'a>(cx: Scope<'a, GeneratedStatsProps<'a>>) -> Element {
+    let filter = &cx.props.filter;
+    let generated = cx.props.stats.generated_tallies(filter);
+    let total = cx.props.stats.tallies(&StatsFilter {
+        generated: true,
+        ..*filter
+    });
+
+    let mut ordered_stats = generated
+        .counts
+        .iter()
+        .filter(|(language, _)| filter.categories.includes(language) || !language.is_known())
+        .map(|(language, counts)| {
+            let total_lines = total.counts.get(language).map_or(0, |counts| counts.lines);
+            (language, counts, total_lines)
+        })
+        .collect::<Vec<_>>();
+
+    if ordered_stats.is_empty() {
+        return None;
+    }
+
+    ordered_stats.sort_by_key(|(_, counts, _)| Reverse(counts.lines));
+    let share = |lines: usize, total_lines: usize| {
+        format!(
+            "{:.1}%",
+            (lines as f64) / (total_lines.max(1) as f64) * 100.
+        )
+    };
+    let generated_files = ordered_stats
+        .iter()
+        .map(|(_, counts, _)| counts.files)
+        .sum::<usize>();
+    let generated_lines = ordered_stats
+        .iter()
+        .map(|(_, counts, _)| counts.lines)
+        .sum::<usize>();
+    let total_lines = ordered_stats
+        .iter()
+        .map(|(_, _, total_lines)| total_lines)
+        .sum::<usize>();
+    let summary = format!(
+        "Generated: {} files, {} lines ({} of lines in these languages){}",
+        format_with_commas(generated_files),
+        format_with_commas(generated_lines),
+        share(generated_lines, total_lines),
+        if filter.generated { "" } else { ", excluded" }
+    );
+
+    render! {
+        details {
+            class: "breakdown-stats",
+
+            summary {
+                class: "breakdown-stats-summary",
+                summary
+            }
+
+            table {
+                class: "breakdown-stats-table",
+
+                thead {
+                    tr {
+                        th { "Language" }
+                        th { "Files" }
+                        th { "Lines" }
+                        th { "Share of lines" }
+                    }
+                }
+
+                tbody {
+                    for (language, counts, total_lines) in ordered_stats {
+                        tr {
+                            td { language.name() }
+                            td { format_with_commas(counts.files) }
+                            td { format_with_commas(counts.lines) }
+                            td { share(counts.lines, total_lines) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
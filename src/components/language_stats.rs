@@ -3,6 +3,7 @@
 use super::LanguageLabel;
 use crate::services::*;
 use dioxus::prelude::*;
+use std::cmp::Reverse;
 use std::collections::HashMap;
 
 /// Language statistics properties.
@@ -14,7 +15,9 @@ where
     /// A label for the statistics.
     label: &'a str,
     /// The statistics.
-    stats: &'a HashMap<String, DirCounts>,
+    stats: &'a DirStats,
+    /// The filter choosing which files are included.
+    filter: StatsFilter,
     /// An function to extract the desired count from the statistics.
     extractor: F,
 }
@@ -24,20 +27,18 @@ pub fn LanguageStats<'a, F>(cx: Scope<'a, LanguageStatsProps<'a, F>>) -> Element
 where
     F: Fn(&DirCounts) -> usize,
 {
-    let filtered_stats = cx
-        .props
-        .stats
+    let tallies = cx.props.stats.tallies(&cx.props.filter);
+    let filtered_stats = tallies
+        .counts
         .iter()
-        .filter_map(|(language, count)| {
-            let lang = Language::new(language);
-            lang.is_known()
-                .then_some((lang, (cx.props.extractor)(count)))
+        .filter(|(language, _)| {
+            cx.props.filter.categories.includes(language) || !language.is_known()
         })
+        .map(|(language, count)| (language.clone(), (cx.props.extractor)(count)))
         .collect::<HashMap<_, _>>();
     let stats_total = filtered_stats.values().sum::<usize>();
     let mut ordered_stats = filtered_stats.into_iter().collect::<Vec<_>>();
-    ordered_stats.sort_by_key(|(_, count)| *count);
-    ordered_stats.reverse();
+    ordered_stats.sort_by_key(|(language, count)| (!language.is_known(), Reverse(*count)));
 
     let stats_label = format!("{} {}", format_with_commas(stats_total), cx.props.label);
 
@@ -56,7 +57,7 @@ where
                 for (language, count) in &ordered_stats {
                     div {
                         class: "lang-stats-bar-item",
-                        background_color: language.color(),
+                        background_color: format_args!("{}", language.color()),
                         flex_grow: *count as i64
                     }
                 }
@@ -67,7 +68,7 @@ where
 
                 for (language, count) in &ordered_stats {
                     LanguageLabel {
-                        language: *language,
+                        language: language.clone(),
                         count: *count,
                         total: stats_total
                     }
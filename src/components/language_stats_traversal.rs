@@ -5,7 +5,8 @@ use super::LanguageLabel;
 use crate::icons::*;
 use crate::services::*;
 use dioxus::prelude::*;
-use std::path::{Path, PathBuf};
+use std::ffi::OsStr;
+use std::path::Path;
 
 /// Language statistics traversal properties.
 #[derive(Props)]
@@ -14,8 +15,11 @@ pub struct LanguageStatsTraversalProps<'a> {
     subpath: &'a Path,
     /// The statistics at the current level of the traversal.
     dir_stats: &'a DirStats,
+    /// The filter choosing which files are considered when labeling
+    /// directories.
+    filter: StatsFilter,
     /// The callback to trigger traversal down the directory structure.
-    on_traverse_down: EventHandler<'a, &'a str>,
+    on_traverse_down: EventHandler<'a, &'a OsStr>,
     /// The callback to trigger traversal up the directory structure.
     on_traverse_up: EventHandler<'a, ()>,
 }
@@ -46,7 +50,7 @@ pub fn LanguageStatsTraversal<'a>(cx: Scope<'a, LanguageStatsTraversalProps<'a>>
                     button {
                         r#type: "button",
                         class: "icon-button",
-                        disabled: cx.props.subpath == PathBuf::from("") || cx.props.subpath == PathBuf::from("/"),
+                        disabled: cx.props.subpath == Path::new("") || cx.props.subpath == Path::new("/"),
                         onclick: move |_| cx.props.on_traverse_up.call(()),
 
                         Icon {
@@ -65,12 +69,12 @@ pub fn LanguageStatsTraversal<'a>(cx: Scope<'a, LanguageStatsTraversalProps<'a>>
                             class: "lang-stats-traversal-dir-info",
 
                             cx.props.dir_stats.dirs.iter().map(|(name, stats)| {
-                                let primary_language = stats.primary_language();
+                                let primary_language = stats.primary_language(&cx.props.filter);
 
                                 render! {
                                     div {
                                         class: "lang-stats-traversal-dir-info-dir",
-                                        onclick: move |_| cx.props.on_traverse_down.call(name.as_str()),
+                                        onclick: move |_| cx.props.on_traverse_down.call(name),
 
                                         div {
                                             class: "lang-stats-traversal-dir-info-label",
@@ -80,7 +84,7 @@ pub fn LanguageStatsTraversal<'a>(cx: Scope<'a, LanguageStatsTraversalProps<'a>>
                                                 class: "folder-icon"
                                             }
                                             span {
-                                                name.as_str()
+                                                name.to_string()
                                             }
                                         }
 
@@ -97,9 +101,7 @@ pub fn LanguageStatsTraversal<'a>(cx: Scope<'a, LanguageStatsTraversalProps<'a>>
                                 }
                             })
 
-                            cx.props.dir_stats.files.keys().map(|name| {
-                                let language = Language::new(PathBuf::from(&**name).extension().and_then(|s| s.to_str()).unwrap_or_default());
-
+                            cx.props.dir_stats.files.iter().map(|(name, stats)| {
                                 render! {
                                     div {
                                         class: "lang-stats-traversal-dir-info-file",
@@ -112,13 +114,33 @@ pub fn LanguageStatsTraversal<'a>(cx: Scope<'a, LanguageStatsTraversalProps<'a>>
                                                 class: "file-icon"
                                             }
                                             span {
-                                                name.as_str()
+                                                name.to_string()
                                             }
                                         }
 
                                         div {
-                                            LanguageLabel {
-                                                language: language
+                                            if let Some(encoding) = stats.encoding.filter(|&encoding| encoding != TextEncoding::Utf8) {
+                                                render! {
+                                                    span {
+                                                        class: "lang-stats-traversal-encoding",
+                                                        encoding.name()
+                                                    }
+                                                }
+                                            }
+
+                                            if let Some(media_type) = stats.media_type {
+                                                render! {
+                                                    span {
+                                                        class: "lang-stats-traversal-media-type",
+                                                        media_type.name()
+                                                    }
+                                                }
+                                            } else {
+                                                render! {
+                                                    LanguageLabel {
+                                                        language: stats.language.clone()
+                                                    }
+                                                }
                                             }
                                         }
                                     }
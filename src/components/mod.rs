@@ -1,21 +1,41 @@
 //! All application components.
 
 mod app;
+mod asset_stats;
+mod component_stats;
 mod file_select;
+mod filter_select;
+mod generated_stats;
 mod icon;
 mod language_label;
 mod language_stats;
 mod language_stats_traversal;
 mod loading;
 mod path_display;
+mod path_filter_editor;
+mod scan_errors;
+mod scan_progress_view;
+mod scan_settings;
 mod stats;
+mod style_health;
+mod unknown_stats;
 
 pub use app::*;
+pub use asset_stats::*;
+pub use component_stats::*;
 pub use file_select::*;
+pub use filter_select::*;
+pub use generated_stats::*;
 pub use icon::*;
 pub use language_label::*;
 pub use language_stats::*;
 pub use language_stats_traversal::*;
 pub use loading::*;
 pub use path_display::*;
+pub use path_filter_editor::*;
+pub use scan_errors::*;
+pub use scan_progress_view::*;
+pub use scan_settings::*;
 pub use stats::*;
+pub use style_health::*;
+pub use unknown_stats::*;
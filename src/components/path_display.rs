@@ -12,11 +12,12 @@ pub struct PathDisplayProps<'a> {
 
 /// Path component.
 pub fn PathDisplay<'a>(cx: Scope<'a, PathDisplayProps<'a>>) -> Element {
-    let components = cx.props.path.iter().filter_map(|component| {
-        component
-            .to_str()
-            .filter(|&component| component != MAIN_SEPARATOR_STR)
-    });
+    let components = cx
+        .props
+        .path
+        .iter()
+        .map(|component| component.to_string_lossy())
+        .filter(|component| component != MAIN_SEPARATOR_STR);
     let num_components = components.clone().count();
     let components = components
         .flat_map(|component| {
@@ -26,7 +27,7 @@ pub fn PathDisplay<'a>(cx: Scope<'a, PathDisplayProps<'a>>) -> Element {
                         class: "path-component",
 
                         span {
-                            component
+                            "{component}"
                         }
                     }
                 },
@@ -0,0 +1,125 @@
+//! Editor for the include and exclude patterns of a scan.
+
+use crate::services::*;
+use dioxus::prelude::*;
+use std::path::Path;
+
+/// Path filter editor properties.
+#[derive(Props)]
+pub struct PathFilterEditorProps<'a> {
+    /// The root of the project the patterns apply to.
+    root: &'a Path,
+    /// The patterns currently applied.
+    filters: &'a PathFilters,
+    /// The callback to apply new patterns.
+    on_apply: EventHandler<'a, PathFilters>,
+}
+
+/// Splits the text of a pattern list into patterns, one per line.
+fn parse_patterns(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(str::to_owned)
+        .collect()
+}
+
+/// Path filter editor component. Patterns are typed one per line, and are
+/// validated before being applied.
+pub fn PathFilterEditor<'a>(cx: Scope<'a, PathFilterEditorProps<'a>>) -> Element {
+    let include = use_state(cx, || cx.props.filters.include.join("\n"));
+    let exclude = use_state(cx, || cx.props.filters.exclude.join("\n"));
+    let error = use_state(cx, || None::<String>);
+
+    let summary = if cx.props.filters.is_empty() {
+        "Path filters: none".to_owned()
+    } else {
+        format!(
+            "Path filters: {} included, {} excluded",
+            cx.props.filters.include.len(),
+            cx.props.filters.exclude.len()
+        )
+    };
+
+    let apply = move |filters: PathFilters| match filters.overrides(cx.props.root) {
+        Ok(_) => {
+            error.set(None);
+            cx.props.on_apply.call(filters);
+        }
+        Err(err) => error.set(Some(err.to_string())),
+    };
+
+    render! {
+        details {
+            class: "breakdown-stats path-filter-editor",
+
+            summary {
+                class: "breakdown-stats-summary",
+                summary
+            }
+
+            div {
+                class: "path-filter-editor-fields",
+
+                label {
+                    class: "path-filter-editor-field",
+
+                    span {
+                        "Include"
+                    }
+                    textarea {
+                        placeholder: "services/**",
+                        value: "{include}",
+                        oninput: move |event| include.set(event.value.clone())
+                    }
+                }
+                label {
+                    class: "path-filter-editor-field",
+
+                    span {
+                        "Exclude"
+                    }
+                    textarea {
+                        placeholder: "third_party/\n**/testdata/**",
+                        value: "{exclude}",
+                        oninput: move |event| exclude.set(event.value.clone())
+                    }
+                }
+            }
+
+            if let Some(err) = &**error {
+                render! {
+                    div {
+                        class: "error path-filter-editor-error",
+                        "Invalid pattern: "
+                        err.as_str()
+                    }
+                }
+            }
+
+            div {
+                class: "path-filter-editor-actions",
+
+                button {
+                    r#type: "button",
+                    class: "button secondary",
+                    onclick: move |_| {
+                        include.set(String::new());
+                        exclude.set(String::new());
+                        apply(PathFilters::default());
+                    },
+                    "Clear"
+                }
+                button {
+                    r#type: "button",
+                    class: "button primary",
+                    onclick: move |_| apply(PathFilters {
+                        include: parse_patterns(include),
+                        exclude: parse_patterns(exclude),
+                    }),
+                    "Apply"
+                }
+            }
+        }
+    }
+}
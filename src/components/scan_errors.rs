@@ -0,0 +1,72 @@
+//! List of the paths that could not be scanned.
+
+use crate::services::*;
+use dioxus::prelude::*;
+use std::path::Path;
+
+/// Scan errors properties.
+#[derive(Props)]
+pub struct ScanErrorsProps<'a> {
+    /// The root of the scan, which paths are shown relative to.
+    root: &'a Path,
+    /// The errors encountered during the scan.
+    errors: &'a [ScanError],
+}
+
+/// Scan errors component. Shows a warning badge with the number of paths that
+/// were skipped, which expands to the list of paths and reasons.
+pub fn ScanErrors<'a>(cx: Scope<'a, ScanErrorsProps<'a>>) -> Element {
+    if cx.props.errors.is_empty() {
+        return None;
+    }
+
+    let summary = format!(
+        "{} {} could not be scanned",
+        format_with_commas(cx.props.errors.len()),
+        if cx.props.errors.len() == 1 {
+            "path"
+        } else {
+            "paths"
+        }
+    );
+
+    render! {
+        details {
+            class: "breakdown-stats scan-errors",
+
+            summary {
+                class: "breakdown-stats-summary",
+
+                span {
+                    class: "scan-errors-badge",
+                    "!"
+                }
+                summary
+            }
+
+            table {
+                class: "breakdown-stats-table",
+
+                thead {
+                    tr {
+                        th { "Path" }
+                        th { "Reason" }
+                        th { "Details" }
+                    }
+                }
+
+                tbody {
+                    for err in cx.props.errors {
+                        tr {
+                            td {
+                                err.path.strip_prefix(cx.props.root).unwrap_or(&err.path).to_string_lossy()
+                            }
+                            td { err.kind.name() }
+                            td { err.message.as_str() }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,85 @@
+//! Live progress of a scan in flight.
+
+use super::Loading;
+use crate::services::*;
+use dioxus::prelude::*;
+use std::path::Path;
+
+/// Scan progress view properties.
+#[derive(Props)]
+pub struct ScanProgressViewProps<'a> {
+    /// The root of the scan, which the current path is shown relative to.
+    root: &'a Path,
+    /// The latest progress of the scan.
+    progress: &'a ScanProgress,
+}
+
+/// Scan progress view component. Shows a spinner with the running counts of
+/// the scan, and a progress bar once the number of files is known.
+pub fn ScanProgressView<'a>(cx: Scope<'a, ScanProgressViewProps<'a>>) -> Element {
+    let progress = cx.props.progress;
+    let counts = format!(
+        "{} directories, {} files, {}",
+        format_with_commas(progress.dirs),
+        format_with_commas(progress.files),
+        format_bytes(progress.bytes as f64)
+    );
+    let rates = format!(
+        "{:.1}s elapsed, {:.0} files/s, {}/s",
+        progress.elapsed().as_secs_f64(),
+        progress.files_per_second(),
+        format_bytes(progress.bytes_per_second())
+    );
+    let current_path = progress.current_path.as_ref().map(|path| {
+        path.strip_prefix(cx.props.root)
+            .unwrap_or(path)
+            .to_string_lossy()
+            .into_owned()
+    });
+
+    render! {
+        div {
+            class: "scan-progress",
+
+            Loading {
+                text: "Fetching code stats..."
+            }
+
+            if let (Some(fraction), Some(total_files)) = (progress.fraction(), progress.total_files) {
+                render! {
+                    div {
+                        class: "scan-progress-bar-container",
+
+                        progress {
+                            class: "scan-progress-bar",
+                            max: 1,
+                            value: fraction
+                        }
+                        span {
+                            class: "scan-progress-percent",
+                            format!("{:.0}% of about {} files", fraction * 100.0, format_with_commas(total_files))
+                        }
+                    }
+                }
+            }
+
+            div {
+                class: "scan-progress-counts",
+                counts
+            }
+            div {
+                class: "scan-progress-rates",
+                rates
+            }
+
+            if let Some(current_path) = current_path {
+                render! {
+                    div {
+                        class: "scan-progress-path",
+                        current_path
+                    }
+                }
+            }
+        }
+    }
+}
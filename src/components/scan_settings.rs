@@ -0,0 +1,255 @@
+//! Scan settings component.
+
+use super::PathDisplay;
+use crate::services::*;
+use dioxus::prelude::*;
+use std::path::Path;
+
+/// The number of bytes in a kibibyte, the unit in which the maximum file size
+/// and read limit are entered.
+const KIBIBYTE: u64 = 1024;
+
+/// Scan settings properties.
+#[derive(Props)]
+pub struct ScanSettingsProps<'a> {
+    /// The path that will be scanned.
+    path: &'a Path,
+    /// The options to start with.
+    options: &'a ScanOptions,
+    /// The callback to start the scan with the chosen options.
+    on_start: EventHandler<'a, ScanOptions>,
+    /// The cancelation callback.
+    on_cancel: EventHandler<'a, ()>,
+}
+
+/// A toggleable scan setting.
+#[derive(Props)]
+struct ScanSettingsToggleProps<'a> {
+    /// The label of the setting.
+    label: &'a str,
+    /// Whether the setting is enabled.
+    checked: bool,
+    /// The callback to signal that the setting has been toggled.
+    on_toggle: EventHandler<'a, bool>,
+}
+
+/// A toggleable scan setting component.
+fn ScanSettingsToggle<'a>(cx: Scope<'a, ScanSettingsToggleProps<'a>>) -> Element {
+    render! {
+        label {
+            class: "scan-settings-option",
+
+            input {
+                r#type: "checkbox",
+                checked: cx.props.checked,
+                onclick: move |_| cx.props.on_toggle.call(!cx.props.checked)
+            }
+            span {
+                cx.props.label
+            }
+        }
+    }
+}
+
+/// Parses an optional number, treating an empty or invalid input as no limit.
+fn parse_limit<T>(value: &str) -> Option<T>
+where
+    T: std::str::FromStr,
+{
+    value.trim().parse().ok()
+}
+
+/// Parses an optional size in kibibytes into bytes, treating an empty or
+/// invalid input, or one too large to count in bytes, as no limit.
+fn parse_size_limit(value: &str) -> Option<u64> {
+    parse_limit::<u64>(value).and_then(|size| size.checked_mul(KIBIBYTE))
+}
+
+/// Scan settings component, shown before a scan is started.
+pub fn ScanSettings<'a>(cx: Scope<'a, ScanSettingsProps<'a>>) -> Element {
+    let options = use_state(cx, || cx.props.options.clone());
+
+    let max_depth = options
+        .max_depth
+        .map(|depth| depth.to_string())
+        .unwrap_or_default();
+    let max_filesize = options
+        .max_filesize
+        .map(|bytes| (bytes / KIBIBYTE).to_string())
+        .unwrap_or_default();
+    let read_limit = options
+        .read_limit
+        .map(|bytes| (bytes / KIBIBYTE).to_string())
+        .unwrap_or_default();
+    let ignore_file_names = options.ignore_file_names.join(", ");
+
+    render! {
+        div {
+            class: "scan-settings",
+
+            div {
+                class: "scan-settings-header",
+
+                span {
+                    "Scan settings"
+                }
+
+                div {
+                    class: "file-select-header-path",
+
+                    PathDisplay {
+                        path: cx.props.path
+                    }
+                }
+            }
+
+            div {
+                class: "scan-settings-body",
+
+                ScanSettingsToggle {
+                    label: "Skip hidden files",
+                    checked: options.hidden,
+                    on_toggle: move |hidden| options.with_mut(|options| options.hidden = hidden)
+                }
+                ScanSettingsToggle {
+                    label: "Respect .gitignore files",
+                    checked: options.git_ignore,
+                    on_toggle: move |git_ignore| options.with_mut(|options| options.git_ignore = git_ignore)
+                }
+                ScanSettingsToggle {
+                    label: "Respect the global git ignore file",
+                    checked: options.git_global,
+                    on_toggle: move |git_global| options.with_mut(|options| options.git_global = git_global)
+                }
+                ScanSettingsToggle {
+                    label: "Respect .git/info/exclude",
+                    checked: options.git_exclude,
+                    on_toggle: move |git_exclude| options.with_mut(|options| options.git_exclude = git_exclude)
+                }
+                ScanSettingsToggle {
+                    label: "Stay on the same file system",
+                    checked: options.same_file_system,
+                    on_toggle: move |same_file_system| options.with_mut(|options| options.same_file_system = same_file_system)
+                }
+                ScanSettingsToggle {
+                    label: "Follow symbolic links",
+                    checked: options.follow_links,
+                    on_toggle: move |follow_links| options.with_mut(|options| options.follow_links = follow_links)
+                }
+                ScanSettingsToggle {
+                    label: "Count line feeds like wc -l",
+                    checked: options.line_count_mode == LineCountMode::Newlines,
+                    on_toggle: move |newlines| options.with_mut(|options| {
+                        options.line_count_mode = if newlines {
+                            LineCountMode::Newlines
+                        } else {
+                            LineCountMode::Lines
+                        };
+                    })
+                }
+                ScanSettingsToggle {
+                    label: "Reuse results of unchanged files from the last scan",
+                    checked: options.cache,
+                    on_toggle: move |cache| options.with_mut(|options| options.cache = cache)
+                }
+
+                label {
+                    class: "scan-settings-field",
+
+                    span {
+                        "Maximum depth"
+                    }
+                    input {
+                        r#type: "number",
+                        min: "0",
+                        placeholder: "Unlimited",
+                        value: "{max_depth}",
+                        oninput: move |event| options.with_mut(|options| options.max_depth = parse_limit(&event.value))
+                    }
+                }
+                label {
+                    class: "scan-settings-field",
+
+                    span {
+                        "Maximum file size (KiB)"
+                    }
+                    input {
+                        r#type: "number",
+                        min: "0",
+                        placeholder: "Unlimited",
+                        value: "{max_filesize}",
+                        oninput: move |event| options.with_mut(|options| {
+                            options.max_filesize = parse_size_limit(&event.value);
+                        })
+                    }
+                }
+                label {
+                    class: "scan-settings-field",
+                    title: "Larger files are only read up to this size, so their lines are counted in part",
+
+                    span {
+                        "Partial read above (KiB)"
+                    }
+                    input {
+                        r#type: "number",
+                        min: "0",
+                        placeholder: "Read whole files",
+                        value: "{read_limit}",
+                        oninput: move |event| options.with_mut(|options| {
+                            options.read_limit = parse_size_limit(&event.value).filter(|&size| size > 0);
+                        })
+                    }
+                }
+                label {
+                    class: "scan-settings-field",
+
+                    span {
+                        "Ignore file names"
+                    }
+                    input {
+                        r#type: "text",
+                        placeholder: DEFAULT_IGNORE_FILE_NAME,
+                        value: "{ignore_file_names}",
+                        onchange: move |event| options.with_mut(|options| {
+                            options.ignore_file_names = event
+                                .value
+                                .split(',')
+                                .map(str::trim)
+                                .filter(|name| !name.is_empty())
+                                .map(str::to_owned)
+                                .collect();
+                        })
+                    }
+                }
+            }
+
+            div {
+                class: "file-select-footer scan-settings-footer",
+
+                button {
+                    r#type: "button",
+                    class: "button secondary",
+                    onclick: move |_| options.set(ScanOptions::default()),
+                    "Reset"
+                }
+
+                div {
+                    class: "file-select-actions",
+
+                    button {
+                        r#type: "button",
+                        class: "button secondary",
+                        onclick: move |_| cx.props.on_cancel.call(()),
+                        "Back"
+                    }
+                    button {
+                        r#type: "button",
+                        class: "button primary",
+                        onclick: move |_| cx.props.on_start.call((**options).clone()),
+                        "Start scan"
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,11 +1,19 @@
 //! Code stats component.
 
-use super::{Icon, LanguageStats, LanguageStatsTraversal, Loading};
+use super::{
+    AssetStats, ComponentStats, FilterSelect, GeneratedStats, Icon, LanguageStats,
+    LanguageStatsTraversal, PathFilterEditor, ScanErrors, ScanProgressView, StyleHealth,
+    UnknownStats,
+};
 use crate::icons::*;
 use crate::services::*;
 use dioxus::prelude::*;
-use std::io;
 use std::path::{Path, PathBuf, MAIN_SEPARATOR_STR};
+use std::time::Duration;
+use tokio::time::sleep;
+
+/// How often the progress of a scan is shown.
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(100);
 
 /// The current state of the code statistics.
 enum CodeStatsState {
@@ -14,7 +22,7 @@ enum CodeStatsState {
     /// Done fetching code statistics information.
     Complete(CodeStats),
     /// An error occurred while fetching code statistics information.
-    Error(io::Error),
+    Error(ServiceError),
 }
 
 /// Code stats properties.
@@ -22,25 +30,157 @@ enum CodeStatsState {
 pub struct StatsProps<'a> {
     /// The path to display code statistics for.
     path: &'a Path,
+    /// The options to scan the path with.
+    options: &'a ScanOptions,
     /// The callback to signal that the user is done viewing the statistics.
     on_done: EventHandler<'a, ()>,
+    /// The callback to signal that the user wants to scan another directory.
+    on_choose_directory: EventHandler<'a, ()>,
+}
+
+/// Gets a short description of what went wrong while fetching code
+/// statistics.
+fn error_title(err: &ServiceError) -> &'static str {
+    match err {
+        ServiceError::InvalidSubpath(_) => "The directory is not part of the statistics",
+        ServiceError::UnreadableRoot { .. } => "The directory could not be read",
+        ServiceError::UnreadableDirectory { .. } => "A directory could not be listed",
+        ServiceError::Cancelled(_) => "The scan was cancelled",
+        ServiceError::Config { .. } => "The scan configuration is invalid",
+    }
 }
 
 /// Code stats component.
 pub fn Stats<'a>(cx: Scope<'a, StatsProps<'a>>) -> Element {
     let status = use_state(cx, || CodeStatsState::Fetching);
     let subpath = use_state(cx, PathBuf::new);
+    let filter = use_state(cx, StatsFilter::default);
+    let path_filters = use_state(cx, || load_path_filters(cx.props.path));
+    let path_filters_error = use_state(cx, || None::<String>);
+    let scan_count = use_state(cx, || 0usize);
+    let scan_cancel = use_state(cx, CancellationToken::new);
+    let scan_progress = use_state(cx, ScanProgress::new);
+    let completed_scans = use_state(cx, || 0usize);
+    let watching = use_state(cx, || false);
+    let watch_error = use_state(cx, || None::<String>);
+    let registry = LanguageRegistry::global();
+
+    use_future(
+        cx,
+        (path_filters.get(), scan_count.get()),
+        |(path_filters, _)| {
+            let path = cx.props.path.to_owned();
+            let options = cx.props.options.clone();
+            to_owned![status, filter, scan_cancel, scan_progress, completed_scans];
+            async move {
+                // Dropping this future, whether because the view was closed or
+                // a new scan was started, stops the scan.
+                let cancel = CancellationToken::new();
+                let _guard = cancel.drop_guard();
+                scan_cancel.set(cancel.clone());
+                scan_progress.set(ScanProgress::new());
+                status.set(CodeStatsState::Fetching);
+
+                let (progress_sender, mut progress_receiver) = progress_channel();
+                let scan = async move {
+                    collect_stats(path, &options, &path_filters, &cancel, &progress_sender).await
+                };
+                let show_progress = async {
+                    while progress_receiver.has_changed().is_ok() {
+                        scan_progress.set(progress_receiver.borrow_and_update().clone());
+                        sleep(PROGRESS_INTERVAL).await;
+                    }
+                };
+                let (result, ()) = tokio::join!(scan, show_progress);
+                scan_progress.set(ScanProgress::new());
+
+                match result {
+                    Ok(stats) => {
+                        if let Some(categories) = stats.config.categories {
+                            filter.with_mut(|filter| filter.categories = categories);
+                        }
 
-    use_on_create(cx, || {
-        let path = cx.props.path.to_owned();
-        to_owned![status];
-        async move {
-            match collect_stats(path).await {
-                Ok(stats) => status.set(CodeStatsState::Complete(stats)),
-                Err(err) => status.set(CodeStatsState::Error(err)),
+                        status.set(CodeStatsState::Complete(stats));
+                        completed_scans.modify(|count| count + 1);
+                    }
+                    Err(err) => status.set(CodeStatsState::Error(err)),
+                }
             }
-        }
-    });
+        },
+    );
+
+    // Starting a scan, or changing the path filters, drops the watcher, so
+    // that it does not replace the statistics of a scan in progress.
+    use_future(
+        cx,
+        (
+            watching.get(),
+            completed_scans.get(),
+            scan_count.get(),
+            path_filters.get(),
+        ),
+        |(watching, _, _, path_filters)| {
+            let path = cx.props.path.to_owned();
+            let options = cx.props.options.clone();
+            to_owned![status, scan_count, watch_error];
+            async move {
+                if !watching {
+                    watch_error.set(None);
+                    return;
+                }
+
+                // Patches are applied to a copy of the completed statistics,
+                // which is shown again after each batch of changes. Copies
+                // share the directories that are not patched.
+                let mut stats = match &*status.current() {
+                    CodeStatsState::Complete(stats) => stats.clone(),
+                    _ => return,
+                };
+
+                let mut watcher = match StatsWatcher::new(&path) {
+                    Ok(watcher) => watcher,
+                    Err(err) => {
+                        watch_error.set(Some(err.to_string()));
+                        return;
+                    }
+                };
+                let mut updater = match StatsUpdater::new(&path, &options, &path_filters) {
+                    Ok(updater) => updater,
+                    Err(err) => {
+                        watch_error.set(Some(err.to_string()));
+                        return;
+                    }
+                };
+                watch_error.set(None);
+
+                while let Some(changes) = watcher.changes().await {
+                    let patches = if changes.rescan {
+                        None
+                    } else {
+                        updater.patches(&stats, &changes.paths).await
+                    };
+
+                    let Some(patches) = patches else {
+                        scan_count.modify(|count| count + 1);
+                        return;
+                    };
+
+                    if patches.is_empty() {
+                        continue;
+                    }
+
+                    if !matches!(*status.current(), CodeStatsState::Complete(_)) {
+                        return;
+                    }
+
+                    patches
+                        .into_iter()
+                        .for_each(|patch| stats.apply_patch(patch));
+                    status.set(CodeStatsState::Complete(stats.clone()));
+                }
+            }
+        },
+    );
 
     let stats_path_str = cx
         .props
@@ -49,98 +189,347 @@ pub fn Stats<'a>(cx: Scope<'a, StatsProps<'a>>) -> Element {
         .filter_map(|s| (s.to_str() != Some(MAIN_SEPARATOR_STR)).then_some(s.to_string_lossy()))
         .collect::<Vec<_>>()
         .join("/");
-    let stats_subpath_str = subpath
+
+    let stats = match &**status {
+        CodeStatsState::Fetching => match &scan_progress.partial {
+            Some(partial) => &**partial,
+            None => {
+                return render! {
+                    div {
+                        class: "stats-fetching",
+
+                        ScanProgressView {
+                            root: cx.props.path,
+                            progress: scan_progress
+                        }
+                        button {
+                            r#type: "button",
+                            class: "button secondary",
+                            onclick: move |_| {
+                                scan_cancel.cancel();
+                                cx.props.on_choose_directory.call(());
+                            },
+                            "Cancel"
+                        }
+                    }
+                };
+            }
+        },
+        CodeStatsState::Complete(stats) => stats,
+        CodeStatsState::Error(err) => {
+            let is_config_error = matches!(err, ServiceError::Config { .. });
+
+            return render! {
+                div {
+                    class: "stats-error",
+
+                    div {
+                        class: "error stats-error-title",
+                        error_title(err)
+                    }
+                    div {
+                        class: "stats-error-details",
+                        err.to_string()
+                    }
+
+                    div {
+                        class: "stats-error-actions",
+
+                        if is_config_error {
+                            render! {
+                                button {
+                                    r#type: "button",
+                                    class: "button secondary",
+                                    onclick: move |_| {
+                                        if let Err(err) = save_path_filters(cx.props.path, &PathFilters::default()) {
+                                            path_filters_error.set(Some(err.to_string()));
+                                        }
+
+                                        path_filters.set(PathFilters::default());
+                                        scan_count.modify(|count| count + 1);
+                                    },
+                                    "Clear path filters"
+                                }
+                            }
+                        }
+
+                        button {
+                            r#type: "button",
+                            class: "button secondary",
+                            onclick: move |_| cx.props.on_choose_directory.call(()),
+                            "Choose another directory"
+                        }
+                        button {
+                            r#type: "button",
+                            class: "button primary",
+                            onclick: move |_| scan_count.modify(|count| count + 1),
+                            "Retry"
+                        }
+                    }
+                }
+            };
+        }
+    };
+    let in_progress = matches!(**status, CodeStatsState::Fetching);
+    let partial_files = stats
+        .stats
+        .tallies
+        .values()
+        .map(Tallies::partial_files)
+        .sum::<usize>();
+
+    // A directory that is no longer part of the statistics, such as after a
+    // rescan with other filters, is left for its deepest remaining ancestor.
+    let (shown_subpath, substats) = subpath
+        .ancestors()
+        .find_map(|ancestor| Some((ancestor, stats.stats_slice(ancestor).ok()?)))
+        .unwrap_or((Path::new(""), &stats.stats));
+
+    if shown_subpath != subpath.as_path() {
+        subpath.set(shown_subpath.to_path_buf());
+    }
+
+    let stats_subpath_str = shown_subpath
         .iter()
         .map(|s| s.to_string_lossy())
         .collect::<Vec<_>>()
         .join("/");
+    let config_files = stats
+        .config
+        .files
+        .iter()
+        .map(|file| file.to_string_lossy())
+        .collect::<Vec<_>>()
+        .join(", ");
 
-    match &**status {
-        CodeStatsState::Fetching => {
-            render! {
-                Loading {
-                    class: "stats-fetching",
-                    text: "Fetching code stats..."
-                }
-            }
-        }
-        CodeStatsState::Complete(stats) => {
-            let substats = stats.stats_slice(&**subpath).unwrap();
+    render! {
+        div {
+            class: "stats-container",
+
+            div {
+                class: "stats",
 
-            render! {
                 div {
-                    class: "stats-container",
+                    class: "stats-header",
 
                     div {
-                        class: "stats",
-
                         div {
-                            class: "stats-header",
+                            class: "stats-path-container",
 
-                            div {
-                                div {
-                                    class: "stats-path-container",
+                            span {
+                                class: "stats-path",
+                                stats_path_str
+                            }
+                            span {
+                                class: "stats-subpath",
+                                "/"
+                                stats_subpath_str
+                            }
+                        }
 
-                                    span {
-                                        class: "stats-path",
-                                        stats_path_str
-                                    }
-                                    span {
-                                        class: "stats-subpath",
-                                        "/"
-                                        stats_subpath_str
-                                    }
+                        if !stats.config.files.is_empty() {
+                            render! {
+                                div {
+                                    class: "stats-config",
+                                    "Project configuration: "
+                                    config_files
                                 }
                             }
+                        }
+                    }
 
-                            div {
-                                button {
-                                    r#type: "button",
-                                    class: "icon-button",
-                                    onclick: move |_| cx.props.on_done.call(()),
+                    div {
+                        class: "stats-header-actions",
 
-                                    Icon {
-                                        data: XMARK
+                        label {
+                            class: "stats-watch",
+                            title: "Update the statistics as files change",
+
+                            input {
+                                r#type: "checkbox",
+                                checked: **watching,
+                                disabled: in_progress,
+                                onclick: move |_| watching.set(!**watching)
+                            }
+                            "Watch for changes"
+                        }
+                        button {
+                            r#type: "button",
+                            class: "icon-button",
+                            onclick: move |_| cx.props.on_done.call(()),
+
+                            Icon {
+                                data: XMARK
+                            }
+                        }
+                    }
+                }
+
+                if in_progress {
+                    render! {
+                        div {
+                            class: "stats-in-progress",
+
+                            span {
+                                class: "stats-in-progress-badge",
+                                "In progress"
+                            }
+                            span {
+                                class: "stats-in-progress-text",
+                                format!(
+                                    "{} files counted so far; the statistics update as the scan continues",
+                                    format_with_commas(scan_progress.files)
+                                )
+                            }
+                            if let Some(fraction) = scan_progress.fraction() {
+                                render! {
+                                    progress {
+                                        class: "stats-in-progress-bar",
+                                        max: 1,
+                                        value: fraction
                                     }
                                 }
                             }
+                            button {
+                                r#type: "button",
+                                class: "button secondary",
+                                onclick: move |_| {
+                                    scan_cancel.cancel();
+                                    cx.props.on_choose_directory.call(());
+                                },
+                                "Cancel"
+                            }
                         }
+                    }
+                }
 
-                        LanguageStats {
-                            label: "files",
-                            stats: &substats.counts,
-                            extractor: |counts| counts.files
+                if let Some(err) = registry.user_definitions_error() {
+                    render! {
+                        div {
+                            class: "warning stats-warning",
+                            "Failed to load user language definitions: "
+                            err
                         }
-                        LanguageStats {
-                            label: "lines",
-                            stats: &substats.counts,
-                            extractor: |counts| counts.lines
+                    }
+                }
+
+                ScanErrors {
+                    root: &stats.path,
+                    errors: &stats.errors
+                }
+
+                for err in &stats.config.errors {
+                    div {
+                        class: "warning stats-warning",
+                        "Invalid project configuration: "
+                        err.as_str()
+                    }
+                }
+
+                if partial_files > 0 {
+                    render! {
+                        div {
+                            class: "warning stats-warning",
+                            format!(
+                                "{} files are larger than the read limit, so only the lines in their first {} were counted",
+                                format_with_commas(partial_files),
+                                format_bytes(cx.props.options.read_limit.unwrap_or_default() as f64)
+                            )
                         }
-                        LanguageStats {
-                            label: "bytes",
-                            stats: &substats.counts,
-                            extractor: |counts| counts.bytes
+                    }
+                }
+
+                if let Some(err) = &**watch_error {
+                    render! {
+                        div {
+                            class: "warning stats-warning",
+                            "Failed to watch for changes: "
+                            err.as_str()
                         }
-                        LanguageStatsTraversal {
-                            subpath: subpath,
-                            dir_stats: substats,
-                            on_traverse_down: move |dir| {
-                                subpath.set(subpath.join(dir));
-                            },
-                            on_traverse_up: move |_| {
-                                subpath.set(subpath.parent().unwrap_or(Path::new("")).to_path_buf());
-                            }
+                    }
+                }
+
+                if let Some(err) = &**path_filters_error {
+                    render! {
+                        div {
+                            class: "warning stats-warning",
+                            "Failed to save path filters: "
+                            err.as_str()
                         }
                     }
                 }
-            }
-        }
-        CodeStatsState::Error(err) => {
-            render! {
-                div {
-                    class: "error stats-error",
-                    "An error occurred while fetching code stats: "
-                    err.to_string()
+
+                PathFilterEditor {
+                    root: cx.props.path,
+                    filters: path_filters,
+                    on_apply: move |new_path_filters: PathFilters| {
+                        match save_path_filters(cx.props.path, &new_path_filters) {
+                            Ok(()) => path_filters_error.set(None),
+                            Err(err) => path_filters_error.set(Some(err.to_string())),
+                        }
+
+                        subpath.set(PathBuf::new());
+                        path_filters.set(new_path_filters);
+                    }
+                }
+                FilterSelect {
+                    filter: **filter,
+                    on_change: move |new_filter| filter.set(new_filter)
+                }
+                LanguageStats {
+                    label: "files",
+                    stats: substats,
+                    filter: **filter,
+                    extractor: |counts| counts.files
+                }
+                LanguageStats {
+                    label: "lines",
+                    stats: substats,
+                    filter: **filter,
+                    extractor: |counts| counts.lines
+                }
+                LanguageStats {
+                    label: "code lines",
+                    stats: substats,
+                    filter: **filter,
+                    extractor: |counts| counts.code
+                }
+                LanguageStats {
+                    label: "bytes",
+                    stats: substats,
+                    filter: **filter,
+                    extractor: |counts| counts.bytes
+                }
+                GeneratedStats {
+                    stats: substats,
+                    filter: **filter
+                }
+                UnknownStats {
+                    stats: substats,
+                    filter: **filter
+                }
+                AssetStats {
+                    stats: substats,
+                    filter: **filter
+                }
+                ComponentStats {
+                    stats: substats,
+                    filter: **filter
+                }
+                StyleHealth {
+                    stats: substats,
+                    filter: **filter
+                }
+                LanguageStatsTraversal {
+                    subpath: shown_subpath,
+                    dir_stats: substats,
+                    filter: **filter,
+                    on_traverse_down: move |dir| {
+                        subpath.set(subpath.join(dir));
+                    },
+                    on_traverse_up: move |_| {
+                        subpath.set(subpath.parent().unwrap_or(Path::new("")).to_path_buf());
+                    }
                 }
             }
         }
@@ -0,0 +1,183 @@
+//! Breakdown of line ending and whitespace style issues.
+
+use crate::services::*;
+use dioxus::prelude::*;
+use std::cmp::Reverse;
+
+/// The most offending files listed at once.
+const MAX_LISTED_FILES: usize = 100;
+
+/// Style health properties.
+#[derive(Props)]
+pub struct StyleHealthProps<'a> {
+    /// The statistics.
+    stats: &'a DirStats,
+    /// The filter choosing which files are included.
+    filter: StatsFilter,
+}
+
+/// Style health component. Checks text files against LF line endings, the
+/// indentation expected in their language, no trailing whitespace and a final
+/// newline, and shows the issues found in each language along with the files
+/// that have them.
+pub fn StyleHealth<'a>(cx: Scope<'a, StyleHealthProps<'a>>) -> Element {
+    let filter = &cx.props.filter;
+    let tallies = cx.props.stats.tallies(filter);
+    let mut ordered_stats = tallies
+        .counts
+        .iter()
+        .filter(|(language, counts)| {
+            filter.categories.includes(language) && counts.style.files_with_issues > 0
+        })
+        .collect::<Vec<_>>();
+
+    if ordered_stats.is_empty() {
+        return None;
+    }
+
+    ordered_stats.sort_by_key(|(_, counts)| Reverse(counts.style.files_with_issues));
+    let offenders = cx.props.stats.style_offenders(filter);
+    let text_files = tallies
+        .counts
+        .iter()
+        .filter(|(language, _)| filter.categories.includes(language))
+        .map(|(_, counts)| counts.files)
+        .sum::<usize>();
+    let summary = format!(
+        "Style health: {} of {} files with issues",
+        format_with_commas(offenders.len()),
+        format_with_commas(text_files)
+    );
+    let unlisted = offenders.len().saturating_sub(MAX_LISTED_FILES);
+    let listed = offenders
+        .iter()
+        .take(MAX_LISTED_FILES)
+        .filter_map(|(path, file)| Some((path, file.style?, file.language.indentation())))
+        .map(|(path, style, expected)| {
+            let indentation = match style.indent_width {
+                Some(width) if style.indentation() == IndentStyle::Spaces => {
+                    format!("Spaces ({width})")
+                }
+                _ => style.indentation().name().to_owned(),
+            };
+
+            (
+                path.to_string_lossy(),
+                style.line_endings().name(),
+                indentation,
+                style_issues(&style, expected).join(", "),
+            )
+        })
+        .collect::<Vec<_>>();
+
+    render! {
+        details {
+            class: "breakdown-stats style-health",
+
+            summary {
+                class: "breakdown-stats-summary",
+                summary
+            }
+
+            table {
+                class: "breakdown-stats-table",
+
+                thead {
+                    tr {
+                        th { "Language" }
+                        th { "Files with issues" }
+                        th { "Not LF" }
+                        th { "Unexpected indentation" }
+                        th { "Trailing whitespace" }
+                        th { "No final newline" }
+                    }
+                }
+
+                tbody {
+                    for (language, counts) in ordered_stats {
+                        tr {
+                            td { language.name() }
+                            td {
+                                format!(
+                                    "{} of {}",
+                                    format_with_commas(counts.style.files_with_issues),
+                                    format_with_commas(counts.files)
+                                )
+                            }
+                            td { format_with_commas(counts.style.non_lf_files()) }
+                            td { format_with_commas(counts.style.unexpected_indentation_files) }
+                            td {
+                                format!(
+                                    "{} ({} lines)",
+                                    format_with_commas(counts.style.trailing_whitespace_files),
+                                    format_with_commas(counts.style.trailing_whitespace_lines)
+                                )
+                            }
+                            td { format_with_commas(counts.style.missing_final_newline_files) }
+                        }
+                    }
+                }
+            }
+
+            table {
+                class: "breakdown-stats-table style-health-files",
+
+                thead {
+                    tr {
+                        th { "Path" }
+                        th { "Line endings" }
+                        th { "Indentation" }
+                        th { "Issues" }
+                    }
+                }
+
+                tbody {
+                    for (path, line_endings, indentation, issues) in listed {
+                        tr {
+                            td { path }
+                            td { line_endings }
+                            td { indentation }
+                            td { issues }
+                        }
+                    }
+                }
+            }
+
+            if unlisted > 0 {
+                render! {
+                    p {
+                        class: "style-health-more",
+                        format!("And {} more files", format_with_commas(unlisted))
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Describes the ways in which a file departs from the style checked for,
+/// given the indentation expected in its language.
+fn style_issues(style: &FileStyle, expected: IndentStyle) -> Vec<String> {
+    let mut issues = Vec::new();
+
+    if style.has_non_lf_line_endings() {
+        issues.push(format!("{} line endings", style.line_endings().name()));
+    }
+
+    if style.has_unexpected_indentation(expected) {
+        issues.push(format!("{} indentation", style.indentation().name()));
+    }
+
+    if style.trailing_whitespace_lines > 0 {
+        issues.push(format!(
+            "Trailing whitespace on {} lines",
+            format_with_commas(style.trailing_whitespace_lines)
+        ));
+    }
+
+    if style.missing_final_newline {
+        issues.push("No final newline".to_owned());
+    }
+
+    issues
+}
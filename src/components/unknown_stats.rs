@@ -0,0 +1,72 @@
+//! Breakdown of files in unrecognized languages.
+
+use crate::services::*;
+use dioxus::prelude::*;
+use std::cmp::Reverse;
+
+/// Unknown file statistics properties.
+#[derive(Props)]
+pub struct UnknownStatsProps<'a> {
+    /// The statistics.
+    stats: &'a DirStats,
+    /// The filter choosing which files are included.
+    filter: StatsFilter,
+}
+
+/// Unknown file statistics component. Lists the extensions and extension-less
+/// file names that could not be mapped to a language, so that they can be
+/// mapped or ignored.
+pub fn UnknownStats<'a>(cx: Scope<'a, UnknownStatsProps<'a>>) -> Element {
+    let tallies = cx.props.stats.tallies(&cx.props.filter);
+
+    if tallies.unknown.is_empty() {
+        return None;
+    }
+
+    let mut ordered_stats = tallies.unknown.iter().collect::<Vec<_>>();
+    ordered_stats.sort_by_key(|(_, counts)| Reverse(counts.files));
+    let total_files = ordered_stats
+        .iter()
+        .map(|(_, counts)| counts.files)
+        .sum::<usize>();
+    let summary = format!(
+        "Other: {} files of {} unrecognized types",
+        format_with_commas(total_files),
+        format_with_commas(ordered_stats.len())
+    );
+
+    render! {
+        details {
+            class: "breakdown-stats",
+
+            summary {
+                class: "breakdown-stats-summary",
+                summary
+            }
+
+            table {
+                class: "breakdown-stats-table",
+
+                thead {
+                    tr {
+                        th { "Type" }
+                        th { "Files" }
+                        th { "Lines" }
+                        th { "Bytes" }
+                    }
+                }
+
+                tbody {
+                    for (file_type, counts) in ordered_stats {
+                        tr {
+                            td { file_type.as_str() }
+                            td { format_with_commas(counts.files) }
+                            td { format_with_commas(counts.lines) }
+                            td { format_with_commas(counts.bytes) }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,294 @@
+//! Support for linguist attributes in `.gitattributes` files.
+
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The name of git attributes files.
+pub const GIT_ATTRIBUTES_FILE_NAME: &str = ".gitattributes";
+
+/// The path of the repository-wide attributes file, relative to the root.
+const GIT_INFO_ATTRIBUTES_PATH: &str = ".git/info/attributes";
+
+/// Tags describing why a file might be excluded from language statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct FileTags {
+    /// Whether the file is generated.
+    pub generated: bool,
+    /// Whether the file is vendored third-party code.
+    pub vendored: bool,
+    /// Whether the file is documentation.
+    pub documentation: bool,
+}
+
+/// The tags set or unset by attributes files. Each tag is `None` when no
+/// attributes file specifies it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct AttributeTags {
+    /// Whether the file is generated.
+    pub generated: Option<bool>,
+    /// Whether the file is vendored third-party code.
+    pub vendored: Option<bool>,
+    /// Whether the file is documentation.
+    pub documentation: Option<bool>,
+}
+
+impl AttributeTags {
+    /// Applies the tags specified by attributes files on top of tags detected
+    /// by other means.
+    pub fn apply(&self, tags: FileTags) -> FileTags {
+        FileTags {
+            generated: self.generated.unwrap_or(tags.generated),
+            vendored: self.vendored.unwrap_or(tags.vendored),
+            documentation: self.documentation.unwrap_or(tags.documentation),
+        }
+    }
+}
+
+/// A linguist attribute.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Attribute {
+    /// The `linguist-generated` attribute.
+    Generated,
+    /// The `linguist-vendored` attribute.
+    Vendored,
+    /// The `linguist-documentation` attribute.
+    Documentation,
+}
+
+impl Attribute {
+    /// Gets the attribute with the given name.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "linguist-generated" => Some(Self::Generated),
+            "linguist-vendored" => Some(Self::Vendored),
+            "linguist-documentation" => Some(Self::Documentation),
+            _ => None,
+        }
+    }
+}
+
+/// A single line of an attributes file. The value of each attribute is `None`
+/// when the line resets it to unspecified.
+#[derive(Debug, Clone)]
+struct AttributeRule {
+    /// The matcher for the line's pattern.
+    matcher: Gitignore,
+    /// The attributes set by the line.
+    attributes: Vec<(Attribute, Option<bool>)>,
+}
+
+/// Parses the lines of an attributes file located in the given directory,
+/// keeping only those that set linguist attributes.
+fn parse_attributes(dir: &Path, source: &str) -> Vec<AttributeRule> {
+    source
+        .lines()
+        .filter_map(|line| {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+                return None;
+            }
+
+            let mut fields = line.split_whitespace();
+            let pattern = fields.next()?;
+            let attributes = fields.filter_map(parse_attribute).collect::<Vec<_>>();
+
+            if attributes.is_empty() {
+                return None;
+            }
+
+            let mut builder = GitignoreBuilder::new(dir);
+            builder.add_line(None, pattern).ok()?;
+            let matcher = builder.build().ok()?;
+
+            Some(AttributeRule {
+                matcher,
+                attributes,
+            })
+        })
+        .collect()
+}
+
+/// Parses a single attribute, such as `linguist-vendored`,
+/// `-linguist-generated`, `!linguist-documentation` or
+/// `linguist-generated=false`.
+fn parse_attribute(field: &str) -> Option<(Attribute, Option<bool>)> {
+    if let Some(name) = field.strip_prefix('-') {
+        Some((Attribute::from_name(name)?, Some(false)))
+    } else if let Some(name) = field.strip_prefix('!') {
+        Some((Attribute::from_name(name)?, None))
+    } else if let Some((name, value)) = field.split_once('=') {
+        Some((Attribute::from_name(name)?, Some(value != "false")))
+    } else {
+        Some((Attribute::from_name(field)?, Some(true)))
+    }
+}
+
+/// The linguist attributes of the files within a directory tree, loaded from
+/// the `.gitattributes` files of each directory as it is walked.
+#[derive(Debug, Clone, Default)]
+pub struct GitAttributes {
+    /// The root directory of the tree.
+    root: PathBuf,
+    /// The rules of the attributes file in each directory that has one.
+    rules: HashMap<PathBuf, Vec<AttributeRule>>,
+    /// The rules of the repository-wide attributes file, which take
+    /// precedence over all others.
+    info_rules: Vec<AttributeRule>,
+}
+
+impl GitAttributes {
+    /// Creates an empty set of attributes for the tree at the given root.
+    pub fn new(root: &Path) -> Self {
+        let info_rules = fs::read_to_string(root.join(GIT_INFO_ATTRIBUTES_PATH))
+            .map(|source| parse_attributes(root, &source))
+            .unwrap_or_default();
+
+        Self {
+            root: root.to_path_buf(),
+            rules: HashMap::new(),
+            info_rules,
+        }
+    }
+
+    /// Loads the attributes file of a directory, if it has one. Directories
+    /// must be loaded before the files within them are tagged.
+    pub fn load_dir(&mut self, dir: &Path) {
+        if let Ok(source) = fs::read_to_string(dir.join(GIT_ATTRIBUTES_FILE_NAME)) {
+            let rules = parse_attributes(dir, &source);
+
+            if !rules.is_empty() {
+                self.rules.insert(dir.to_path_buf(), rules);
+            }
+        }
+    }
+
+    /// Gets the tags specified for a file. Attributes files in deeper
+    /// directories take precedence, as do later lines within a file.
+    pub fn tags(&self, path: &Path) -> AttributeTags {
+        let mut dirs = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .collect::<Vec<_>>();
+        dirs.reverse();
+
+        let rules = dirs
+            .into_iter()
+            .filter_map(|dir| self.rules.get(dir))
+            .flatten()
+            .chain(&self.info_rules);
+        let mut values = [None; 3];
+
+        for rule in rules {
+            if rule.matcher.matched(path, false).is_ignore() {
+                for (attribute, value) in &rule.attributes {
+                    values[*attribute as usize] = *value;
+                }
+            }
+        }
+
+        AttributeTags {
+            generated: values[Attribute::Generated as usize],
+            vendored: values[Attribute::Vendored as usize],
+            documentation: values[Attribute::Documentation as usize],
+        }
+    }
+}
+
+/// Git attributes tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Creates a set of attributes from the contents of attributes files in
+    /// the given directories.
+    fn attributes(files: &[(&str, &str)]) -> GitAttributes {
+        let root = Path::new("/repo");
+        let mut attributes = GitAttributes::new(root);
+
+        for (dir, source) in files {
+            let dir = root.join(dir);
+            attributes
+                .rules
+                .insert(dir.clone(), parse_attributes(&dir, source));
+        }
+
+        attributes
+    }
+
+    /// Tests parsing of individual attributes.
+    #[test]
+    fn test_parse_attribute() {
+        use Attribute::*;
+
+        assert_eq!(
+            parse_attribute("linguist-vendored"),
+            Some((Vendored, Some(true)))
+        );
+        assert_eq!(
+            parse_attribute("-linguist-generated"),
+            Some((Generated, Some(false)))
+        );
+        assert_eq!(
+            parse_attribute("linguist-generated=false"),
+            Some((Generated, Some(false)))
+        );
+        assert_eq!(
+            parse_attribute("linguist-documentation=true"),
+            Some((Documentation, Some(true)))
+        );
+        assert_eq!(
+            parse_attribute("!linguist-vendored"),
+            Some((Vendored, None))
+        );
+        assert_eq!(parse_attribute("text"), None);
+        assert_eq!(parse_attribute("eol=lf"), None);
+    }
+
+    /// Tests that files are tagged according to matching patterns.
+    #[test]
+    fn test_tags() {
+        let attributes = attributes(&[
+            (
+                "",
+                "# comment\n*.pb.go linguist-generated\nthird_party/** linguist-vendored\n\
+                 docs/*.md linguist-documentation\n*.txt text eol=lf\n",
+            ),
+            ("third_party/keep", "*.c -linguist-vendored\n"),
+        ]);
+        let tags = |path: &str| {
+            attributes
+                .tags(&Path::new("/repo").join(path))
+                .apply(FileTags::default())
+        };
+
+        assert!(tags("api/service.pb.go").generated);
+        assert!(!tags("api/service.go").generated);
+        assert!(tags("third_party/lib/x.c").vendored);
+        assert!(!tags("third_party/keep/x.c").vendored);
+        assert!(tags("third_party/keep/x.h").vendored);
+        assert!(tags("docs/guide.md").documentation);
+        assert!(!tags("src/docs/guide.md").documentation);
+        assert_eq!(tags("notes.txt"), FileTags::default());
+    }
+
+    /// Tests that explicitly unset attributes override detected tags.
+    #[test]
+    fn test_overrides() {
+        let attributes = attributes(&[("", "*.lock -linguist-generated\n")]);
+        let detected = FileTags {
+            generated: true,
+            ..FileTags::default()
+        };
+
+        let tags = attributes.tags(Path::new("/repo/Cargo.lock"));
+        assert_eq!(tags.generated, Some(false));
+        assert!(!tags.apply(detected).generated);
+        let tags = attributes.tags(Path::new("/repo/app.min.js"));
+        assert_eq!(tags, AttributeTags::default());
+        assert!(tags.apply(detected).generated);
+    }
+}
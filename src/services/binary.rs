@@ -0,0 +1,299 @@
+//! Binary file detection services.
+
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// The number of bytes from the start of a file inspected to decide whether it
+/// is binary.
+const BINARY_SNIFF_BYTES: usize = 8 * 1024;
+
+/// The proportion of invalid UTF-8 bytes above which a file is considered
+/// binary.
+const MAX_INVALID_UTF8_RATIO: f64 = 0.1;
+
+/// The media type of a binary file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum MediaType {
+    /// An image, such as a PNG or JPEG.
+    Image,
+    /// An audio file.
+    Audio,
+    /// A video file.
+    Video,
+    /// A font.
+    Font,
+    /// A compressed archive.
+    Archive,
+    /// An executable, library or other compiled artifact.
+    Executable,
+    /// A document, such as a PDF or office document.
+    Document,
+    /// A database.
+    Database,
+    /// Any other binary file.
+    Other,
+}
+
+impl MediaType {
+    /// Gets the display name of the media type.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Image => "images",
+            Self::Audio => "audio",
+            Self::Video => "video",
+            Self::Font => "fonts",
+            Self::Archive => "archives",
+            Self::Executable => "executables",
+            Self::Document => "documents",
+            Self::Database => "databases",
+            Self::Other => "other binary files",
+        }
+    }
+}
+
+/// Extensions of files that are always binary, and their media types.
+const BINARY_EXTENSIONS: &[(&str, MediaType)] = &[
+    ("png", MediaType::Image),
+    ("jpg", MediaType::Image),
+    ("jpeg", MediaType::Image),
+    ("gif", MediaType::Image),
+    ("bmp", MediaType::Image),
+    ("ico", MediaType::Image),
+    ("icns", MediaType::Image),
+    ("webp", MediaType::Image),
+    ("avif", MediaType::Image),
+    ("heic", MediaType::Image),
+    ("tif", MediaType::Image),
+    ("tiff", MediaType::Image),
+    ("psd", MediaType::Image),
+    ("xcf", MediaType::Image),
+    ("mp3", MediaType::Audio),
+    ("wav", MediaType::Audio),
+    ("ogg", MediaType::Audio),
+    ("flac", MediaType::Audio),
+    ("aac", MediaType::Audio),
+    ("m4a", MediaType::Audio),
+    ("opus", MediaType::Audio),
+    ("mp4", MediaType::Video),
+    ("m4v", MediaType::Video),
+    ("mov", MediaType::Video),
+    ("avi", MediaType::Video),
+    ("mkv", MediaType::Video),
+    ("webm", MediaType::Video),
+    ("wmv", MediaType::Video),
+    ("ttf", MediaType::Font),
+    ("otf", MediaType::Font),
+    ("woff", MediaType::Font),
+    ("woff2", MediaType::Font),
+    ("eot", MediaType::Font),
+    ("zip", MediaType::Archive),
+    ("gz", MediaType::Archive),
+    ("tgz", MediaType::Archive),
+    ("bz2", MediaType::Archive),
+    ("xz", MediaType::Archive),
+    ("zst", MediaType::Archive),
+    ("7z", MediaType::Archive),
+    ("rar", MediaType::Archive),
+    ("tar", MediaType::Archive),
+    ("jar", MediaType::Archive),
+    ("war", MediaType::Archive),
+    ("whl", MediaType::Archive),
+    ("nupkg", MediaType::Archive),
+    ("crate", MediaType::Archive),
+    ("apk", MediaType::Archive),
+    ("dmg", MediaType::Archive),
+    ("iso", MediaType::Archive),
+    ("exe", MediaType::Executable),
+    ("dll", MediaType::Executable),
+    ("so", MediaType::Executable),
+    ("dylib", MediaType::Executable),
+    ("o", MediaType::Executable),
+    ("rlib", MediaType::Executable),
+    ("class", MediaType::Executable),
+    ("pyc", MediaType::Executable),
+    ("pyo", MediaType::Executable),
+    ("wasm", MediaType::Executable),
+    ("pdf", MediaType::Document),
+    ("doc", MediaType::Document),
+    ("docx", MediaType::Document),
+    ("xls", MediaType::Document),
+    ("xlsx", MediaType::Document),
+    ("ppt", MediaType::Document),
+    ("pptx", MediaType::Document),
+    ("odt", MediaType::Document),
+    ("ods", MediaType::Document),
+    ("odp", MediaType::Document),
+    ("sqlite", MediaType::Database),
+    ("sqlite3", MediaType::Database),
+    ("mdb", MediaType::Database),
+];
+
+/// Signatures found at the start of binary files, and their media types.
+const MAGIC_NUMBERS: &[(&[u8], MediaType)] = &[
+    (b"\x89PNG\r\n\x1a\n", MediaType::Image),
+    (b"\xff\xd8\xff", MediaType::Image),
+    (b"GIF87a", MediaType::Image),
+    (b"GIF89a", MediaType::Image),
+    (b"wOFF", MediaType::Font),
+    (b"wOF2", MediaType::Font),
+    (b"OTTO", MediaType::Font),
+    (b"\x00\x01\x00\x00\x00", MediaType::Font),
+    (b"PK\x03\x04", MediaType::Archive),
+    (b"\x1f\x8b", MediaType::Archive),
+    (b"BZh", MediaType::Archive),
+    (b"\xfd7zXZ\x00", MediaType::Archive),
+    (b"7z\xbc\xaf\x27\x1c", MediaType::Archive),
+    (b"\x28\xb5\x2f\xfd", MediaType::Archive),
+    (b"\x7fELF", MediaType::Executable),
+    (b"\xcf\xfa\xed\xfe", MediaType::Executable),
+    (b"\xce\xfa\xed\xfe", MediaType::Executable),
+    (b"\xca\xfe\xba\xbe", MediaType::Executable),
+    (b"\x00asm", MediaType::Executable),
+    (b"%PDF-", MediaType::Document),
+    (b"SQLite format 3\x00", MediaType::Database),
+];
+
+/// The position within a DOS header of the offset of the PE header.
+const PE_OFFSET_POSITION: usize = 0x3c;
+
+/// Byte order marks of UTF-16 text, which contains NUL bytes but is not
+/// binary.
+const UTF16_BOMS: [&[u8]; 2] = [b"\xff\xfe", b"\xfe\xff"];
+
+/// Gets the media type of a file from its extension, if the extension is
+/// known to belong to binary files.
+pub fn binary_media_type_by_extension(path: &Path) -> Option<MediaType> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+
+    BINARY_EXTENSIONS
+        .iter()
+        .find(|(binary_ext, _)| *binary_ext == ext)
+        .map(|(_, media_type)| *media_type)
+}
+
+/// Gets the media type of a file from its content, if the content is binary.
+/// The file is binary if it starts with a known signature, or if the first
+/// block contains a NUL byte or too many bytes that are not valid UTF-8.
+pub fn binary_media_type(content: &[u8]) -> Option<MediaType> {
+    if let Some((_, media_type)) = MAGIC_NUMBERS
+        .iter()
+        .find(|(magic, _)| content.starts_with(magic))
+    {
+        return Some(*media_type);
+    }
+
+    if is_pe_executable(content) {
+        return Some(MediaType::Executable);
+    }
+
+    if UTF16_BOMS.iter().any(|bom| content.starts_with(bom)) {
+        return None;
+    }
+
+    let block = &content[..content.len().min(BINARY_SNIFF_BYTES)];
+
+    if block.contains(&0) {
+        return Some(MediaType::Other);
+    }
+
+    let invalid = invalid_utf8_bytes(block);
+    let ratio = invalid as f64 / block.len().max(1) as f64;
+    (ratio > MAX_INVALID_UTF8_RATIO).then_some(MediaType::Other)
+}
+
+/// Checks whether the content starts with a DOS header pointing to a PE
+/// header, as in Windows executables. The `MZ` signature alone is too short to
+/// tell an executable from text that happens to start with it.
+fn is_pe_executable(content: &[u8]) -> bool {
+    let Some(offset) = content
+        .get(PE_OFFSET_POSITION..PE_OFFSET_POSITION + 4)
+        .filter(|_| content.starts_with(b"MZ"))
+    else {
+        return false;
+    };
+    let offset = u32::from_le_bytes(offset.try_into().unwrap()) as usize;
+
+    content
+        .get(offset..)
+        .is_some_and(|header| header.starts_with(b"PE\0\0"))
+}
+
+/// Counts the bytes that are not part of valid UTF-8 sequences. A sequence
+/// truncated by the end of the block is not counted.
+pub fn invalid_utf8_bytes(mut block: &[u8]) -> usize {
+    let mut invalid = 0;
+
+    loop {
+        match std::str::from_utf8(block) {
+            Ok(_) => return invalid,
+            Err(err) => match err.error_len() {
+                Some(len) => {
+                    invalid += len;
+                    block = &block[err.valid_up_to() + len..];
+                }
+                None => return invalid,
+            },
+        }
+    }
+}
+
+/// Binary detection tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests detection from known binary extensions.
+    #[test]
+    fn test_extensions() {
+        let media_type = |name| binary_media_type_by_extension(Path::new(name));
+        assert_eq!(media_type("logo.PNG"), Some(MediaType::Image));
+        assert_eq!(media_type("fonts/Inter.woff2"), Some(MediaType::Font));
+        assert_eq!(media_type("app.exe"), Some(MediaType::Executable));
+        assert_eq!(media_type("main.rs"), None);
+        assert_eq!(media_type("Makefile"), None);
+
+        // Extensions shared with text formats are decided by content.
+        assert_eq!(media_type("teapot.obj"), None);
+        assert_eq!(media_type("dump.db"), None);
+    }
+
+    /// Tests detection from file content.
+    #[test]
+    fn test_content() {
+        assert_eq!(
+            binary_media_type(b"\x89PNG\r\n\x1a\n\x00\x00"),
+            Some(MediaType::Image)
+        );
+        assert_eq!(
+            binary_media_type(b"SQLite format 3\x00\x10\x00"),
+            Some(MediaType::Database)
+        );
+        assert_eq!(binary_media_type(b"abc\x00def"), Some(MediaType::Other));
+        assert_eq!(
+            binary_media_type(&[0x80, 0x81, b'a', 0xfe, 0xff, b'b']),
+            Some(MediaType::Other)
+        );
+        assert_eq!(
+            binary_media_type("fn main() {}\n// héllo ✓\n".as_bytes()),
+            None
+        );
+        assert_eq!(binary_media_type(b"\xff\xfeh\x00i\x00"), None);
+        assert_eq!(binary_media_type(b"MZ is a text file\n"), None);
+
+        let mut executable = b"MZ".to_vec();
+        executable.resize(0x40, 0);
+        executable[0x3c] = 0x40;
+        executable.extend_from_slice(b"PE\0\0");
+        assert_eq!(binary_media_type(&executable), Some(MediaType::Executable));
+        assert_eq!(binary_media_type(b""), None);
+    }
+
+    /// Tests that a multi-byte character cut off by the end of the block is
+    /// not counted as invalid.
+    #[test]
+    fn test_truncated_utf8() {
+        assert_eq!(invalid_utf8_bytes("ab✓".as_bytes()), 0);
+        assert_eq!(invalid_utf8_bytes(&"ab✓".as_bytes()[..4]), 0);
+        assert_eq!(invalid_utf8_bytes(b"a\xffb\xfe"), 2);
+    }
+}
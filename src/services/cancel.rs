@@ -0,0 +1,64 @@
+//! Cooperative cancellation of long-running services.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// A token used to signal that a service should stop. Clones of a token share
+/// the same state, so any clone can cancel the work observing the others.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    /// Creates a new token that has not been cancelled.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Signals cancellation to all clones of the token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    /// Checks whether the token has been cancelled.
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+
+    /// Creates a guard that cancels the token when it is dropped.
+    pub fn drop_guard(&self) -> CancelOnDrop {
+        CancelOnDrop(self.clone())
+    }
+}
+
+/// A guard that cancels its token when dropped, stopping work that is no
+/// longer being awaited.
+#[derive(Debug)]
+pub struct CancelOnDrop(CancellationToken);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.cancel();
+    }
+}
+
+/// Cancellation tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that cancellation is shared between clones and guards.
+    #[test]
+    fn test_cancellation_token() {
+        let token = CancellationToken::new();
+        let clone = token.clone();
+        assert!(!token.is_cancelled());
+        clone.cancel();
+        assert!(token.is_cancelled());
+
+        let token = CancellationToken::new();
+        let guard = token.drop_guard();
+        assert!(!token.is_cancelled());
+        drop(guard);
+        assert!(token.is_cancelled());
+    }
+}
@@ -1,56 +1,80 @@
 //! Code statistics services.
 
-use super::TaskPool;
 use crate::services::*;
-use ignore::Walk;
-use std::borrow::Borrow;
-use std::cmp::Ordering;
+use ignore::{DirEntry, WalkState};
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
 use std::collections::{BTreeMap, HashMap};
-use std::io;
+use std::ffi::{OsStr, OsString};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Read, Seek, SeekFrom};
 use std::ops::Deref;
 use std::path::{Path, PathBuf};
-use tokio::fs::File;
-use tokio::io::AsyncReadExt;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
 use tokio::spawn;
-use tokio::sync::mpsc::channel;
+use tokio::sync::mpsc::{channel, Sender};
+use tokio::task::spawn_blocking;
 
-/// The default task pool size.
-const TASK_POOL_SIZE: usize = 20;
+/// The number of scanned entries that may wait to be collected before the
+/// walker threads wait for the collection to catch up.
+const STATS_CHANNEL_SIZE: usize = 1024;
+
+/// The number of bytes at the start of a file used to detect its type and
+/// language.
+const SAMPLE_HEAD_BYTES: u64 = 64 * 1024;
+
+/// The number of bytes at the end of a larger file also used to detect its
+/// language.
+const SAMPLE_TAIL_BYTES: u64 = 4 * 1024;
+
+/// The size of the chunks in which files are read and counted, which bounds
+/// the memory used by each walker thread.
+const READ_CHUNK_BYTES: usize = 256 * 1024;
+
+/// The shortest time between partial snapshots of a scan in progress.
+const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How many times longer than a snapshot takes to create the scan waits
+/// before creating the next, so that large trees are not copied constantly.
+const SNAPSHOT_BACKOFF: u32 = 4;
 
 /// A wrapper around a file or directory name. This is necessary so that file
-/// and directory names will disregard case when ordering.
+/// and directory names will disregard case when ordering. Names need not be
+/// valid UTF-8, and are displayed lossily.
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
-pub struct Name(String);
+pub struct Name(OsString);
 
 impl Deref for Name {
-    type Target = String;
+    type Target = OsStr;
 
     fn deref(&self) -> &Self::Target {
         &self.0
     }
 }
 
-impl Borrow<str> for Name {
-    fn borrow(&self) -> &str {
-        self.0.as_str()
+impl fmt::Display for Name {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.to_string_lossy())
     }
 }
 
-impl Borrow<String> for Name {
-    fn borrow(&self) -> &String {
-        &self.0
+impl From<&OsStr> for Name {
+    fn from(value: &OsStr) -> Self {
+        Self(value.to_owned())
     }
 }
 
-impl From<&str> for Name {
-    fn from(value: &str) -> Self {
-        Self(value.to_owned())
+impl From<OsString> for Name {
+    fn from(value: OsString) -> Self {
+        Self(value)
     }
 }
 
-impl From<String> for Name {
-    fn from(value: String) -> Self {
-        Self(value)
+impl From<&str> for Name {
+    fn from(value: &str) -> Self {
+        Self(value.into())
     }
 }
 
@@ -62,28 +86,54 @@ impl PartialOrd for Name {
 
 impl Ord for Name {
     fn cmp(&self, other: &Self) -> Ordering {
-        match self.0.to_lowercase().cmp(&other.0.to_lowercase()) {
-            Ordering::Equal => self.0.cmp(other),
+        let lowercase = |name: &OsStr| name.to_string_lossy().to_lowercase();
+
+        match lowercase(&self.0).cmp(&lowercase(&other.0)) {
+            Ordering::Equal => self.0.cmp(&other.0),
             other => other,
         }
     }
 }
 
 /// Tallied statistics for a single file.
-#[derive(Debug, Clone, Copy, PartialEq, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
 pub struct FileCounts {
-    /// The number of lines in the file.
+    /// The number of lines in the file, counted according to the line count
+    /// mode of the scan.
     pub lines: usize,
+    /// The number of lines containing code.
+    pub code: usize,
+    /// The number of lines containing only comments.
+    pub comments: usize,
+    /// The number of lines containing only whitespace.
+    pub blanks: usize,
     /// The number of bytes in the file.
     pub bytes: usize,
+    /// Whether only the start of the file was read, so that its lines are
+    /// only counted within that part.
+    pub partial: bool,
 }
 
 /// Code statistics for a single file.
 #[derive(Debug, Clone, PartialEq)]
 pub struct FileStats {
-    /// The language of the file.
-    pub language: String,
-    /// The tallied statistics.
+    /// The language of the file. Binary files are never attributed to a
+    /// language.
+    pub language: Language,
+    /// The media type of the file, if it is binary.
+    pub media_type: Option<MediaType>,
+    /// The encoding of the file, if it is text.
+    pub encoding: Option<TextEncoding>,
+    /// The line ending and whitespace style of the file, if it is text.
+    pub style: Option<FileStyle>,
+    /// The tags of the file, used to exclude it from statistics. Tags are
+    /// detected from the file's name and content, and may be overridden by
+    /// attributes files.
+    pub tags: FileTags,
+    /// The component the file belongs to, as configured by project
+    /// configuration files.
+    pub component: Option<String>,
+    /// The tallied statistics. Only bytes are counted for binary files.
     pub counts: FileCounts,
 }
 
@@ -94,153 +144,579 @@ pub struct DirCounts {
     pub files: usize,
     /// The number of lines written in a given language.
     pub lines: usize,
+    /// The number of lines of code written in a given language.
+    pub code: usize,
+    /// The number of comment lines written in a given language.
+    pub comments: usize,
+    /// The number of blank lines written in a given language.
+    pub blanks: usize,
     /// The number of bytes written in a given language.
     pub bytes: usize,
+    /// The number of files whose lines were only counted in part.
+    pub partial_files: usize,
+    /// The tallied line ending and whitespace styles.
+    pub style: StyleCounts,
+}
+
+impl DirCounts {
+    /// Adds the statistics of a single file.
+    fn add_file(&mut self, file: &FileStats) {
+        let counts = &file.counts;
+        self.files += 1;
+        self.lines += counts.lines;
+        self.code += counts.code;
+        self.comments += counts.comments;
+        self.blanks += counts.blanks;
+        self.bytes += counts.bytes;
+        self.partial_files += usize::from(counts.partial);
+
+        if let Some(style) = &file.style {
+            self.style.add_file(style, file.language.indentation());
+        }
+    }
+
+    /// Adds the statistics of another directory.
+    fn add_dir(&mut self, counts: &DirCounts) {
+        self.files += counts.files;
+        self.lines += counts.lines;
+        self.code += counts.code;
+        self.comments += counts.comments;
+        self.blanks += counts.blanks;
+        self.bytes += counts.bytes;
+        self.partial_files += counts.partial_files;
+        self.style.add(&counts.style);
+    }
+
+    /// Removes the statistics of another directory.
+    fn remove_dir(&mut self, counts: &DirCounts) {
+        self.files = self.files.saturating_sub(counts.files);
+        self.lines = self.lines.saturating_sub(counts.lines);
+        self.code = self.code.saturating_sub(counts.code);
+        self.comments = self.comments.saturating_sub(counts.comments);
+        self.blanks = self.blanks.saturating_sub(counts.blanks);
+        self.bytes = self.bytes.saturating_sub(counts.bytes);
+        self.partial_files = self.partial_files.saturating_sub(counts.partial_files);
+        self.style.remove(&counts.style);
+    }
+}
+
+/// Tallied statistics for binary files of a single media type.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct AssetCounts {
+    /// The number of files.
+    pub files: usize,
+    /// The number of bytes.
+    pub bytes: usize,
+}
+
+/// Options controlling which files count towards statistics.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StatsFilter {
+    /// The categories of languages to include.
+    pub categories: CategorySelection,
+    /// Whether to include generated files.
+    pub generated: bool,
+    /// Whether to include vendored files.
+    pub vendored: bool,
+    /// Whether to include documentation.
+    pub documentation: bool,
+}
+
+impl StatsFilter {
+    /// Gets whether files with the given tags are included.
+    pub fn includes_tags(&self, tags: FileTags) -> bool {
+        (self.generated || !tags.generated)
+            && (self.vendored || !tags.vendored)
+            && (self.documentation || !tags.documentation)
+    }
+}
+
+/// Tallied statistics for a group of files.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Tallies {
+    /// A mapping of languages to their tallied statistics.
+    pub counts: HashMap<Language, DirCounts>,
+    /// A mapping of unrecognized file types to their tallied statistics. Files
+    /// are grouped by lowercase extension, such as `.dat`, or by their full
+    /// name when they have no extension.
+    pub unknown: BTreeMap<String, DirCounts>,
+    /// A mapping of media types to the tallied statistics of binary files,
+    /// which are excluded from the language statistics.
+    pub assets: BTreeMap<MediaType, AssetCounts>,
+    /// A mapping of component names to the tallied statistics of the text
+    /// files belonging to them.
+    pub components: BTreeMap<String, DirCounts>,
+}
+
+impl Tallies {
+    /// Adds the statistics of a single file.
+    fn add_file(&mut self, name: &OsStr, file: &FileStats) {
+        if let Some(media_type) = file.media_type {
+            let entry = self.assets.entry(media_type).or_default();
+            entry.files += 1;
+            entry.bytes += file.counts.bytes;
+            return;
+        }
+
+        self.counts
+            .entry(file.language.clone())
+            .or_default()
+            .add_file(file);
+
+        if !file.language.is_known() {
+            self.unknown
+                .entry(unknown_file_type(name))
+                .or_default()
+                .add_file(file);
+        }
+
+        if let Some(component) = &file.component {
+            self.components
+                .entry(component.clone())
+                .or_default()
+                .add_file(file);
+        }
+    }
+
+    /// Adds another set of tallied statistics.
+    fn add(&mut self, other: &Tallies) {
+        other.counts.iter().for_each(|(language, counts)| {
+            self.counts
+                .entry(language.clone())
+                .or_default()
+                .add_dir(counts);
+        });
+        other.unknown.iter().for_each(|(file_type, counts)| {
+            self.unknown
+                .entry(file_type.clone())
+                .or_default()
+                .add_dir(counts);
+        });
+        other.assets.iter().for_each(|(media_type, counts)| {
+            let entry = self.assets.entry(*media_type).or_default();
+            entry.files += counts.files;
+            entry.bytes += counts.bytes;
+        });
+        other.components.iter().for_each(|(component, counts)| {
+            self.components
+                .entry(component.clone())
+                .or_default()
+                .add_dir(counts);
+        });
+    }
+
+    /// Removes another set of tallied statistics, dropping the entries that no
+    /// longer count any files.
+    fn remove(&mut self, other: &Tallies) {
+        other.counts.iter().for_each(|(language, counts)| {
+            if let Some(entry) = self.counts.get_mut(language) {
+                entry.remove_dir(counts);
+            }
+        });
+        other.unknown.iter().for_each(|(file_type, counts)| {
+            if let Some(entry) = self.unknown.get_mut(file_type) {
+                entry.remove_dir(counts);
+            }
+        });
+        other.assets.iter().for_each(|(media_type, counts)| {
+            if let Some(entry) = self.assets.get_mut(media_type) {
+                entry.files = entry.files.saturating_sub(counts.files);
+                entry.bytes = entry.bytes.saturating_sub(counts.bytes);
+            }
+        });
+        other.components.iter().for_each(|(component, counts)| {
+            if let Some(entry) = self.components.get_mut(component) {
+                entry.remove_dir(counts);
+            }
+        });
+
+        self.counts.retain(|_, counts| counts.files > 0);
+        self.unknown.retain(|_, counts| counts.files > 0);
+        self.assets.retain(|_, counts| counts.files > 0);
+        self.components.retain(|_, counts| counts.files > 0);
+    }
+
+    /// Checks whether no files are tallied.
+    fn is_empty(&self) -> bool {
+        self.counts.is_empty() && self.assets.is_empty()
+    }
+
+    /// Gets the number of text files whose lines were only counted in part.
+    pub fn partial_files(&self) -> usize {
+        self.counts
+            .values()
+            .chain(self.unknown.values())
+            .map(|counts| counts.partial_files)
+            .sum()
+    }
+
+    /// Gets the most prevalent language among those in the selected
+    /// categories.
+    pub fn primary_language(&self, categories: CategorySelection) -> Option<Language> {
+        let mut stats_vec = self.counts.iter().collect::<Vec<_>>();
+        stats_vec.sort_by_key(|(_, counts)| Reverse(counts.bytes));
+
+        if !stats_vec.is_empty() {
+            Some(
+                stats_vec
+                    .iter()
+                    .find(|(language, _)| categories.includes(language))
+                    .map(|(language, _)| (*language).clone())
+                    .unwrap_or_else(Language::unknown),
+            )
+        } else {
+            None
+        }
+    }
 }
 
 /// Code statistics for a directory.
 #[derive(Debug, Clone, PartialEq, Default)]
 pub struct DirStats {
     /// A mapping of subdirectory names to their code statistics.
-    pub dirs: BTreeMap<Name, DirStats>,
+    /// Subdirectories are shared between copies of the statistics, so that
+    /// updating a copy only copies the directories along the updated path.
+    pub dirs: BTreeMap<Name, Arc<DirStats>>,
     /// A mapping of file names to their code statistics.
     pub files: BTreeMap<Name, FileStats>,
-    /// A mapping of languages to their tallied statistics.
-    pub counts: HashMap<String, DirCounts>,
+    /// The tallied statistics of all files in the directory, grouped by the
+    /// files' tags.
+    pub tallies: HashMap<FileTags, Tallies>,
 }
 
 impl DirStats {
     /// Inserts a new directory into the data structure.
     fn insert_dir(&mut self, path: &Path) {
-        if let Some(first) = path.iter().next().and_then(|s| s.to_str()) {
+        if let Some(first) = path.iter().next() {
             let rest = path.strip_prefix(first).unwrap();
 
-            self.dirs
-                .entry(Name::from(first))
-                .or_default()
-                .insert_dir(rest);
+            Arc::make_mut(self.dirs.entry(Name::from(first)).or_default()).insert_dir(rest);
         }
     }
 
     /// Inserts a new file with its statistics into the data structure.
     fn insert_file(&mut self, path: &Path, stats: FileStats) {
         let mut path_iter = path.iter();
+        let first = path_iter.next().unwrap();
 
-        if let Some(first) = path_iter.next().unwrap().to_str() {
-            match path_iter.next() {
-                None => {
-                    self.files.insert(Name::from(first), stats);
-                }
-                Some(_) => {
-                    let rest = path.strip_prefix(first).unwrap();
+        match path_iter.next() {
+            None => {
+                self.files.insert(Name::from(first), stats);
+            }
+            Some(_) => {
+                let rest = path.strip_prefix(first).unwrap();
+
+                Arc::make_mut(self.dirs.entry(Name::from(first)).or_default())
+                    .insert_file(rest, stats);
+            }
+        }
+    }
+
+    /// Inserts a file into statistics that have already been tallied,
+    /// updating the tallies of every directory containing it.
+    fn insert_tallied_file(&mut self, path: &Path, stats: FileStats) {
+        let name = path.file_name().unwrap_or_default();
+        self.tallies
+            .entry(stats.tags)
+            .or_default()
+            .add_file(name, &stats);
+
+        let mut path_iter = path.iter();
+        let first = path_iter.next().unwrap();
+
+        match path_iter.next() {
+            None => {
+                self.files.insert(Name::from(first), stats);
+            }
+            Some(_) => {
+                let rest = path.strip_prefix(first).unwrap();
 
-                    self.dirs
-                        .entry(Name::from(first))
-                        .or_default()
-                        .insert_file(rest, stats);
+                Arc::make_mut(self.dirs.entry(Name::from(first)).or_default())
+                    .insert_tallied_file(rest, stats);
+            }
+        }
+    }
+
+    /// Removes a file or directory from statistics that have already been
+    /// tallied, updating the tallies of every directory containing it. Returns
+    /// the tallies that were removed.
+    fn remove_path(&mut self, path: &Path) -> HashMap<FileTags, Tallies> {
+        let mut path_iter = path.iter();
+        let Some(first) = path_iter.next() else {
+            return HashMap::new();
+        };
+        let name = Name::from(first);
+
+        let removed = match path_iter.next() {
+            None => {
+                if let Some(file) = self.files.remove(&name) {
+                    let mut tallies = HashMap::<FileTags, Tallies>::new();
+                    tallies.entry(file.tags).or_default().add_file(first, &file);
+                    tallies
+                } else if let Some(dir) = self.dirs.remove(&name) {
+                    Arc::unwrap_or_clone(dir).tallies
+                } else {
+                    HashMap::new()
                 }
             }
+            Some(_) => match self.dirs.get_mut(&name) {
+                Some(dir) => Arc::make_mut(dir).remove_path(path.strip_prefix(first).unwrap()),
+                None => HashMap::new(),
+            },
+        };
+
+        for (tags, tallies) in &removed {
+            if let Some(entry) = self.tallies.get_mut(tags) {
+                entry.remove(tallies);
+            }
         }
+
+        self.tallies.retain(|_, tallies| !tallies.is_empty());
+        removed
     }
 
     /// Calculates stats for the directory and updates them in-place.
     fn tally_dir_stats(&mut self) {
-        self.dirs.values_mut().for_each(|dir| dir.tally_dir_stats());
+        self.dirs
+            .values_mut()
+            .for_each(|dir| Arc::make_mut(dir).tally_dir_stats());
 
-        self.files.values().for_each(|file| {
-            let entry = self.counts.entry(file.language.clone()).or_default();
-            entry.files += 1;
-            entry.lines += file.counts.lines;
-            entry.bytes += file.counts.bytes;
+        self.files.iter().for_each(|(name, file)| {
+            self.tallies
+                .entry(file.tags)
+                .or_default()
+                .add_file(name, file);
         });
 
         self.dirs.values().for_each(|dir| {
-            dir.counts.iter().for_each(|(language, counts)| {
-                let entry = self.counts.entry(language.clone()).or_default();
-                entry.files += counts.files;
-                entry.lines += counts.lines;
-                entry.bytes += counts.bytes;
-            })
+            dir.tallies.iter().for_each(|(tags, tallies)| {
+                self.tallies.entry(*tags).or_default().add(tallies);
+            });
         });
     }
 
+    /// Gets the tallied statistics of the files included by the filter.
+    pub fn tallies(&self, filter: &StatsFilter) -> Tallies {
+        let mut included = Tallies::default();
+
+        self.tallies
+            .iter()
+            .filter(|(tags, _)| filter.includes_tags(**tags))
+            .for_each(|(_, tallies)| included.add(tallies));
+
+        included
+    }
+
     /// Gets a subsection of statistics given a path.
-    pub fn stats_slice<P>(&self, subpath: P) -> io::Result<&DirStats>
+    pub fn stats_slice<P>(&self, subpath: P) -> ServiceResult<&DirStats>
     where
         P: AsRef<Path>,
     {
         let subpath = subpath.as_ref();
 
-        match subpath.iter().next() {
-            Some(next_component) => {
-                let next_stats = match self.dirs.get(&Name::from(next_component.to_str().unwrap()))
-                {
-                    Some(value) => Ok(value),
-                    None => Err(io::Error::new(
-                        io::ErrorKind::NotFound,
-                        "invalid directory subpath",
-                    )),
-                }?;
-                let next_subpath = subpath.strip_prefix(next_component).unwrap();
-                next_stats.stats_slice(next_subpath)
-            }
-            None => Ok(self),
-        }
+        subpath.iter().try_fold(self, |stats, component| {
+            stats
+                .dirs
+                .get(&Name::from(component))
+                .map(|dir| &**dir)
+                .ok_or_else(|| ServiceError::InvalidSubpath(subpath.to_path_buf()))
+        })
     }
 
-    /// Gets the extension of the most prevalent language in the directory.
-    pub fn primary_language(&self) -> Option<Language> {
-        let mut stats_vec = self.counts.iter().collect::<Vec<_>>();
-        stats_vec.sort_by(|(_, counts1), (_, counts2)| counts2.bytes.cmp(&counts1.bytes));
+    /// Gets the tallied statistics of the generated files that the filter
+    /// would include if it included generated files.
+    pub fn generated_tallies(&self, filter: &StatsFilter) -> Tallies {
+        let filter = StatsFilter {
+            generated: true,
+            ..*filter
+        };
+        let mut generated = Tallies::default();
 
-        if !stats_vec.is_empty() {
-            Some(
-                stats_vec
-                    .iter()
-                    .find_map(|(language, _)| {
-                        let lang = Language::new(language);
-                        lang.is_known().then_some(lang)
-                    })
-                    .unwrap_or(Language::Unknown),
-            )
-        } else {
-            None
-        }
+        self.tallies
+            .iter()
+            .filter(|(tags, _)| tags.generated && filter.includes_tags(**tags))
+            .for_each(|(_, tallies)| generated.add(tallies));
+
+        generated
+    }
+
+    /// Gets the most prevalent language in the directory among the files
+    /// included by the filter.
+    pub fn primary_language(&self, filter: &StatsFilter) -> Option<Language> {
+        self.tallies(filter).primary_language(filter.categories)
+    }
+
+    /// Gets the text files included by the filter whose style has issues,
+    /// with their paths relative to the directory, in path order.
+    pub fn style_offenders(&self, filter: &StatsFilter) -> Vec<(PathBuf, &FileStats)> {
+        let mut offenders = Vec::new();
+        self.collect_style_offenders(Path::new(""), filter, &mut offenders);
+        offenders.sort_by(|(a, _), (b, _)| a.cmp(b));
+        offenders
+    }
+
+    /// Collects the files within the directory whose style has issues.
+    fn collect_style_offenders<'a>(
+        &'a self,
+        path: &Path,
+        filter: &StatsFilter,
+        offenders: &mut Vec<(PathBuf, &'a FileStats)>,
+    ) {
+        self.dirs.iter().for_each(|(name, dir)| {
+            dir.collect_style_offenders(&path.join(&**name), filter, offenders);
+        });
+
+        self.files
+            .iter()
+            .filter(|(_, file)| {
+                filter.includes_tags(file.tags) && filter.categories.includes(&file.language)
+            })
+            .filter(|(_, file)| {
+                file.style
+                    .is_some_and(|style| style.has_issues(file.language.indentation()))
+            })
+            .for_each(|(name, file)| offenders.push((path.join(&**name), file)));
+    }
+}
+
+/// Gets the type of an unrecognized file, used to group unrecognized files.
+/// This is the lowercase extension including the leading dot, or the full file
+/// name if the file has no extension.
+fn unknown_file_type(name: &OsStr) -> String {
+    match Path::new(name).extension() {
+        Some(ext) => format!(".{}", ext.to_string_lossy().to_lowercase()),
+        None => name.to_string_lossy().into_owned(),
     }
 }
 
-/// Collects code statistics for a given file.
-async fn file_stats<P>(path: P) -> io::Result<FileStats>
+/// Collects code statistics for a given file. The language is detected unless
+/// one is given. The file is read in chunks, so memory use does not depend on
+/// its size, and only the start of it is read if it is larger than the read
+/// limit of the scan options. UTF-16 text is decoded before its lines are
+/// counted. Reading and counting block, so this runs on blocking threads.
+pub fn file_stats<P>(
+    registry: &LanguageRegistry,
+    path: P,
+    language: Option<Language>,
+    options: &ScanOptions,
+) -> io::Result<FileStats>
 where
     P: AsRef<Path>,
 {
-    let mut file = File::open(&path).await?;
-    let size = file.metadata().await.map(|m| m.len() as usize).ok();
-    let mut data = Vec::with_capacity(size.unwrap_or(0));
-    file.read_to_end(&mut data).await?;
-
-    let language = path
-        .as_ref()
-        .extension()
-        .unwrap_or_default()
-        .to_str()
-        .unwrap()
-        .to_owned();
-    let lines = data.iter().fold(0, |total, this_char| {
-        if *this_char == b'\n' {
-            total + 1
-        } else {
-            total
+    let path = path.as_ref();
+    let mut file = File::open(path)?;
+    let size = file.metadata()?.len();
+
+    if let Some(media_type) = binary_media_type_by_extension(path) {
+        return Ok(binary_file_stats(media_type, size as usize));
+    }
+
+    let mut sample = Vec::new();
+    (&mut file)
+        .take(SAMPLE_HEAD_BYTES)
+        .read_to_end(&mut sample)?;
+    let head_len = sample.len();
+
+    if let Some(media_type) = binary_media_type(&sample) {
+        return Ok(binary_file_stats(media_type, size as usize));
+    }
+
+    // Modelines may also be at the end of a file, so the end of a file larger
+    // than the head is added to the sample. The end starts at an even offset,
+    // so that UTF-16 code units are not split.
+    if head_len as u64 == SAMPLE_HEAD_BYTES && size > SAMPLE_HEAD_BYTES {
+        let tail_start = size
+            .saturating_sub(SAMPLE_TAIL_BYTES)
+            .max(SAMPLE_HEAD_BYTES)
+            & !1;
+        file.seek(SeekFrom::Start(tail_start))?;
+        (&mut file)
+            .take(SAMPLE_TAIL_BYTES)
+            .read_to_end(&mut sample)?;
+        file.seek(SeekFrom::Start(SAMPLE_HEAD_BYTES))?;
+    }
+
+    let encoding = TextEncoding::detect(&sample[..head_len]);
+    let text_sample = encoding.decode_sample(&sample);
+    let language = language.unwrap_or_else(|| detect_language(registry, path, &text_sample));
+    let tags = FileTags {
+        generated: is_generated(path, &text_sample),
+        ..FileTags::default()
+    };
+    let read_limit = options
+        .read_limit
+        .filter(|&limit| limit > 0 && size > limit);
+    let limit = read_limit.unwrap_or(u64::MAX);
+    let mut decoder = TextDecoder::new(encoding);
+    let mut counter = LineCounter::new(language.comment_syntax(), options.line_count_mode);
+    let mut style = StyleDetector::new();
+    let head_read = head_len.min(limit as usize);
+    let head = decoder.decode(&sample[encoding.bom().len().min(head_read)..head_read]);
+    counter.feed(&head);
+    style.feed(&head);
+    let mut bytes = head_read;
+
+    // A head shorter than the sample holds the whole file.
+    if head_len as u64 == SAMPLE_HEAD_BYTES && SAMPLE_HEAD_BYTES < limit {
+        let mut reader = file.take(limit - SAMPLE_HEAD_BYTES);
+        let mut chunk = vec![0; READ_CHUNK_BYTES];
+
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => break,
+                Ok(read) => {
+                    let text = decoder.decode(&chunk[..read]);
+                    counter.feed(&text);
+                    style.feed(&text);
+                    bytes += read;
+                }
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
         }
-    }) + 1;
-    let bytes = data.len();
+    }
+
+    if let Some(rest) = decoder.finish() {
+        counter.feed(&rest);
+        style.feed(&rest);
+    }
+
+    let mut counts = counter.finish();
+    counts.bytes = bytes;
+
+    if read_limit.is_some() {
+        counts.bytes = size as usize;
+        counts.partial = true;
+    }
 
     Ok(FileStats {
         language,
-        counts: FileCounts { lines, bytes },
+        media_type: None,
+        encoding: Some(encoding),
+        style: Some(style.finish(read_limit.is_none())),
+        tags,
+        component: None,
+        counts,
     })
 }
 
+/// Creates the statistics for a binary file, for which only bytes are
+/// counted.
+fn binary_file_stats(media_type: MediaType, bytes: usize) -> FileStats {
+    FileStats {
+        language: Language::unknown(),
+        media_type: Some(media_type),
+        encoding: None,
+        style: None,
+        tags: FileTags::default(),
+        component: None,
+        counts: FileCounts {
+            bytes,
+            ..FileCounts::default()
+        },
+    }
+}
+
 /// Statistics on a codebase.
 #[derive(Debug, Clone, PartialEq)]
 pub struct CodeStats {
@@ -248,6 +724,10 @@ pub struct CodeStats {
     pub path: PathBuf,
     /// The statistics.
     pub stats: DirStats,
+    /// The project configuration applied to the scan.
+    pub config: ProjectConfigSummary,
+    /// The paths that could not be scanned, ordered by path.
+    pub errors: Vec<ScanError>,
 }
 
 impl CodeStats {
@@ -258,24 +738,26 @@ impl CodeStats {
     {
         Self {
             path: path.as_ref().to_path_buf(),
-            stats: DirStats {
-                dirs: BTreeMap::new(),
-                files: BTreeMap::new(),
-                counts: HashMap::new(),
-            },
+            stats: DirStats::default(),
+            config: ProjectConfigSummary::default(),
+            errors: Vec::new(),
         }
     }
 
-    /// Inserts a new directory into the data structure.
+    /// Inserts a new directory into the data structure. Paths outside the
+    /// root are skipped.
     fn insert_dir(&mut self, path: &Path) {
-        let relative_path = path.strip_prefix(&self.path).unwrap();
-        self.stats.insert_dir(relative_path);
+        if let Ok(relative_path) = path.strip_prefix(&self.path) {
+            self.stats.insert_dir(relative_path);
+        }
     }
 
-    /// Inserts a new file with its statistics into the data structure.
+    /// Inserts a new file with its statistics into the data structure. Paths
+    /// outside the root are skipped.
     fn insert_file(&mut self, path: &Path, stats: FileStats) {
-        let relative_path = path.strip_prefix(&self.path).unwrap();
-        self.stats.insert_file(relative_path, stats);
+        if let Ok(relative_path) = path.strip_prefix(&self.path) {
+            self.stats.insert_file(relative_path, stats);
+        }
     }
 
     /// Calculates stats for the directory and updates them in-place.
@@ -283,93 +765,1120 @@ impl CodeStats {
         self.stats.tally_dir_stats();
     }
 
+    /// Tallies the statistics once all paths have been inserted.
+    fn finish(&mut self) {
+        self.tally_dir_stats();
+        self.errors.sort_by(|a, b| a.path.cmp(&b.path));
+    }
+
+    /// Creates a tallied copy of statistics that are still being collected.
+    fn snapshot(&self) -> Self {
+        let mut snapshot = self.clone();
+        snapshot.finish();
+        snapshot
+    }
+
     /// Gets a subsection of statistics given a path.
-    pub fn stats_slice<P>(&self, subpath: P) -> io::Result<&DirStats>
+    pub fn stats_slice<P>(&self, subpath: P) -> ServiceResult<&DirStats>
     where
         P: AsRef<Path>,
     {
         self.stats.stats_slice(subpath)
     }
 
-    /// Gets the extension of the most prevalent language in the directory.
+    /// Checks whether the statistics contain a directory, given its full path.
+    pub fn contains_dir(&self, path: &Path) -> bool {
+        path.strip_prefix(&self.path)
+            .is_ok_and(|relative_path| self.stats_slice(relative_path).is_ok())
+    }
+
+    /// Applies a change observed after the statistics were collected, updating
+    /// the tallies of the directories affected. Changes to paths outside the
+    /// root are dropped.
+    pub fn apply_patch(&mut self, patch: StatsPatch) {
+        match patch {
+            StatsPatch::Remove(path) => {
+                if let Ok(relative_path) = path.strip_prefix(&self.path) {
+                    self.stats.remove_path(relative_path);
+                }
+
+                self.errors.retain(|err| !err.path.starts_with(&path));
+            }
+            StatsPatch::Dir(path) => {
+                self.insert_dir(&path);
+            }
+            StatsPatch::File(path, stats) => {
+                let relative_path = match path.strip_prefix(&self.path) {
+                    Ok(relative_path) if !relative_path.as_os_str().is_empty() => relative_path,
+                    _ => return,
+                };
+                self.stats.remove_path(relative_path);
+                self.stats.insert_tallied_file(relative_path, stats);
+                self.errors.retain(|err| err.path != path);
+            }
+            StatsPatch::Error(err) => {
+                self.errors.retain(|other| other.path != err.path);
+                self.errors.push(err);
+                self.errors.sort_by(|a, b| a.path.cmp(&b.path));
+            }
+        }
+    }
+
+    /// Gets the most prevalent language in the directory among the files
+    /// included by the filter.
     #[allow(dead_code)]
-    pub fn primary_language(&self) -> Option<Language> {
-        self.stats.primary_language()
+    pub fn primary_language(&self, filter: &StatsFilter) -> Option<Language> {
+        self.stats.primary_language(filter)
     }
 }
 
-/// A code statistics item.
+/// A code statistics item, sent from the walk to the collection task.
 #[derive(Debug, Clone, PartialEq)]
-struct StatsItem {
-    /// The full path to the item.
-    path: PathBuf,
-    /// The statistics. `None` indicates that the path is a directory.
-    stats: Option<FileStats>,
+enum StatsItem {
+    /// A directory, given its full path.
+    Dir(PathBuf),
+    /// A file, given its full path and statistics, and the entry to cache for
+    /// it if caching is enabled.
+    File(PathBuf, Box<FileStats>, Option<CachedFile>),
+    /// A path that could not be scanned.
+    Error(ScanError),
 }
 
-/// Collects code statistics for the given directory.
-pub async fn collect_stats<P>(path: P) -> io::Result<CodeStats>
+/// The state shared by the walker threads of a scan.
+struct ScanWalk {
+    /// The root of the scan.
+    root: PathBuf,
+    /// The project configuration, loaded as directories are walked.
+    configs: RwLock<ProjectConfigs>,
+    /// The attributes files, loaded as directories are walked.
+    attributes: RwLock<GitAttributes>,
+    /// The language registry used to detect languages.
+    registry: Arc<LanguageRegistry>,
+    /// The options the tree is scanned with.
+    options: ScanOptions,
+    /// The statistics of files from the previous scan, if caching is enabled.
+    cache: Option<ScanCache>,
+    /// The token that stops the walk when cancelled.
+    cancel: CancellationToken,
+}
+
+impl ScanWalk {
+    /// Scans an entry found by a walker thread, sending what is found to the
+    /// collection task. The walker calls this for a directory before reading
+    /// its entries, so the configuration and attributes of every directory
+    /// are loaded before the files within it are scanned.
+    fn visit(
+        &self,
+        entry: Result<DirEntry, ignore::Error>,
+        sender: &Sender<StatsItem>,
+    ) -> WalkState {
+        if self.cancel.is_cancelled() {
+            return WalkState::Quit;
+        }
+
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(err) => {
+                return send_item(
+                    sender,
+                    StatsItem::Error(ScanError::from_walk(&self.root, &err)),
+                );
+            }
+        };
+        // The entry's metadata only follows symbolic links if the walker
+        // does, so links that are not followed are neither files nor
+        // directories and are skipped.
+        let metadata = entry.metadata();
+        let entry_path = entry.into_path();
+        let metadata = match metadata {
+            Ok(metadata) => metadata,
+            Err(err) => {
+                if self.configs.read().unwrap().is_excluded(&entry_path, false) {
+                    return WalkState::Continue;
+                }
+
+                return send_item(
+                    sender,
+                    StatsItem::Error(ScanError::from_walk(&self.root, &err)),
+                );
+            }
+        };
+
+        if metadata.is_file() {
+            match self.file_item(entry_path, &metadata) {
+                Some(stats_item) => send_item(sender, stats_item),
+                None => WalkState::Continue,
+            }
+        } else if metadata.is_dir() {
+            // Excluded directories are not descended into, since everything
+            // within them is excluded too.
+            if self.configs.read().unwrap().is_excluded(&entry_path, true) {
+                return WalkState::Skip;
+            }
+
+            self.configs.write().unwrap().load_dir(&entry_path);
+            self.attributes.write().unwrap().load_dir(&entry_path);
+            send_item(sender, StatsItem::Dir(entry_path))
+        } else {
+            WalkState::Continue
+        }
+    }
+
+    /// Counts a file, or takes its statistics from the cache if it is
+    /// unchanged. Returns `None` if the file is excluded.
+    fn file_item(&self, entry_path: PathBuf, metadata: &fs::Metadata) -> Option<StatsItem> {
+        let (language, component) = {
+            let configs = self.configs.read().unwrap();
+
+            if configs.is_excluded(&entry_path, false) {
+                return None;
+            }
+
+            (
+                configs.language_override(&entry_path),
+                configs.component(&entry_path),
+            )
+        };
+        let tags = self.attributes.read().unwrap().tags(&entry_path);
+        let fingerprint = FileFingerprint::new(metadata);
+
+        if let Some((cached, mut stats)) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(&entry_path, fingerprint, language.as_ref()))
+            .and_then(|cached| Some((cached.clone(), cached.stats(&self.registry)?)))
+        {
+            stats.tags = tags.apply(stats.tags);
+            stats.component = component;
+            return Some(StatsItem::File(entry_path, Box::new(stats), Some(cached)));
+        }
+
+        let stats_item =
+            match file_stats(&self.registry, &entry_path, language.clone(), &self.options) {
+                Ok(mut stats) => {
+                    let cached = self
+                        .cache
+                        .is_some()
+                        .then(|| CachedFile::new(fingerprint, language.as_ref(), &stats));
+                    stats.tags = tags.apply(stats.tags);
+                    stats.component = component;
+                    StatsItem::File(entry_path, Box::new(stats), cached)
+                }
+                Err(err) => StatsItem::Error(ScanError::from_io(&entry_path, &err)),
+            };
+
+        Some(stats_item)
+    }
+}
+
+/// Sends an item to the collection task from a walker thread, stopping the
+/// walk if the collection has ended.
+fn send_item(sender: &Sender<StatsItem>, stats_item: StatsItem) -> WalkState {
+    match sender.blocking_send(stats_item) {
+        Ok(()) => WalkState::Continue,
+        Err(_) => WalkState::Quit,
+    }
+}
+
+/// Collects code statistics for the given directory, walking it according to
+/// the scan options and skipping the files rejected by the path filters. The
+/// tree is walked, and files are read and counted, on a pool of blocking
+/// walker threads. If the token is cancelled, the walk stops and the scan
+/// fails with `ServiceError::Cancelled`. Progress is published as directories
+/// and files are counted. Files unchanged since the last completed scan are
+/// taken from the scan cache when it is enabled.
+pub async fn collect_stats<P>(
+    path: P,
+    options: &ScanOptions,
+    filters: &PathFilters,
+    cancel: &CancellationToken,
+    progress: &ProgressSender,
+) -> ServiceResult<CodeStats>
 where
     P: AsRef<Path>,
 {
     let path = path.as_ref();
-    let pool = TaskPool::new(TASK_POOL_SIZE);
-    let (stats_sender, mut stats_receiver) = channel::<StatsItem>(TASK_POOL_SIZE);
+    fs::read_dir(path).map_err(|source| ServiceError::UnreadableRoot {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    let overrides = filters
+        .overrides(path)
+        .map_err(|err| ServiceError::Config {
+            path: path.to_path_buf(),
+            message: err.to_string(),
+        })?;
+    let configs = ProjectConfigs::new(path, LanguageRegistry::global());
+    let registry = configs.registry();
+    let cache = options
+        .cache
+        .then(|| scan_cache_path(path))
+        .flatten()
+        .map(|file| ScanCache::load(file, path, &registry, options));
+    let (stats_sender, mut stats_receiver) = channel::<StatsItem>(STATS_CHANNEL_SIZE);
+
+    progress.send_replace(ScanProgress::new());
+    let mut walk_builder = options.walk_builder(path);
+    walk_builder.overrides(overrides);
+    // The pre-count is abandoned once the scan returns, however it ends.
+    let pre_count = CancellationToken::new();
+    let _pre_count_guard = pre_count.drop_guard();
+    spawn_pre_count(walk_builder.clone(), progress.clone(), pre_count);
 
     let stats_collection_task = spawn({
         let path = path.to_path_buf();
+        let progress = progress.clone();
+        let mut new_cache = cache.as_ref().map(ScanCache::empty_copy);
         async move {
             let mut stats = CodeStats::new(path);
+            let mut next_snapshot = Instant::now() + SNAPSHOT_INTERVAL;
 
             while let Some(stats_item) = stats_receiver.recv().await {
-                match stats_item.stats {
-                    Some(file_stats) => {
-                        stats.insert_file(&stats_item.path, file_stats);
+                match stats_item {
+                    StatsItem::Dir(path) => {
+                        stats.insert_dir(&path);
+                        progress.send_modify(|progress| progress.dirs += 1);
                     }
-                    None => {
-                        stats.insert_dir(&stats_item.path);
+                    StatsItem::File(path, file_stats, cached) => {
+                        let bytes = file_stats.counts.bytes;
+                        stats.insert_file(&path, *file_stats);
+
+                        if let (Some(new_cache), Some(cached)) = (&mut new_cache, cached) {
+                            new_cache.insert(path.clone(), cached);
+                        }
+
+                        progress.send_modify(|progress| {
+                            progress.files += 1;
+                            progress.bytes += bytes;
+                            progress.current_path = Some(path);
+                        });
+                    }
+                    StatsItem::Error(err) => {
+                        stats.errors.push(err);
                     }
                 }
+
+                if Instant::now() >= next_snapshot {
+                    let started = Instant::now();
+                    let snapshot = Arc::new(stats.snapshot());
+                    progress.send_modify(|progress| progress.partial = Some(snapshot));
+                    next_snapshot = Instant::now()
+                        + SNAPSHOT_INTERVAL.max(started.elapsed() * SNAPSHOT_BACKOFF);
+                }
             }
 
-            stats.tally_dir_stats();
-            stats
+            stats.finish();
+            (stats, new_cache)
         }
     });
 
-    for entry in Walk::new(path).flatten() {
-        let entry_path = entry.into_path();
-
-        if entry_path.is_file() {
+    let walk = ScanWalk {
+        root: path.to_path_buf(),
+        attributes: RwLock::new(GitAttributes::new(path)),
+        configs: RwLock::new(configs),
+        registry,
+        options: options.clone(),
+        cache,
+        cancel: cancel.clone(),
+    };
+    let walker = walk_builder.build_parallel();
+    let walk_task = spawn_blocking(move || {
+        walker.run(|| {
+            let walk = &walk;
             let stats_sender = stats_sender.clone();
+            Box::new(move |entry| walk.visit(entry, &stats_sender))
+        });
 
-            pool.queue(async move {
-                if let Ok(stats) = file_stats(&entry_path).await {
-                    stats_sender
-                        .send(StatsItem {
-                            path: entry_path,
-                            stats: Some(stats),
-                        })
-                        .await
-                        .unwrap();
-                }
-            })
-            .await;
-        } else if entry_path.is_dir() {
-            stats_sender
-                .send(StatsItem {
-                    path: entry_path,
-                    stats: None,
-                })
+        walk.configs.into_inner().unwrap()
+    });
+
+    let configs = walk_task.await.unwrap();
+    let (mut stats, new_cache) = stats_collection_task.await.unwrap();
+
+    if cancel.is_cancelled() {
+        return Err(ServiceError::Cancelled(path.to_path_buf()));
+    }
+
+    // The cache is only an optimization, so failing to save it is not an
+    // error.
+    if let Some(new_cache) = new_cache {
+        spawn_blocking(move || new_cache.save());
+    }
+
+    // Directories are walked in no particular order, so configuration files
+    // are listed by path.
+    stats.config = configs.summary().clone();
+    stats.config.files.sort();
+    stats.config.errors.sort();
+
+    Ok(stats)
+}
+
+/// Code statistics tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Gets the options used to scan test directories, which do not touch the
+    /// user's scan cache.
+    fn scan_options() -> ScanOptions {
+        ScanOptions {
+            cache: false,
+            ..ScanOptions::default()
+        }
+    }
+
+    /// Creates the statistics of a text file with the given number of lines.
+    fn text_file(language: Language, lines: usize, tags: FileTags) -> FileStats {
+        FileStats {
+            language,
+            media_type: None,
+            encoding: Some(TextEncoding::Utf8),
+            style: Some(FileStyle::default()),
+            tags,
+            component: None,
+            counts: FileCounts {
+                lines,
+                bytes: lines * 10,
+                ..FileCounts::default()
+            },
+        }
+    }
+
+    /// Tests that the primary language honors the category selection.
+    #[test]
+    fn test_primary_language() {
+        let registry = LanguageRegistry::builtin();
+        let json = registry.find_by_alias("json").unwrap();
+        let rust = registry.find_by_alias("rust").unwrap();
+        let mut stats = DirStats::default();
+        stats.insert_file(
+            Path::new("fixtures.json"),
+            text_file(json.clone(), 100, FileTags::default()),
+        );
+        stats.insert_file(
+            Path::new("main.rs"),
+            text_file(rust.clone(), 1, FileTags::default()),
+        );
+        stats.insert_file(
+            Path::new("data.bin"),
+            text_file(Language::unknown(), 500, FileTags::default()),
+        );
+        stats.tally_dir_stats();
+
+        let filter = StatsFilter::default();
+        assert_eq!(stats.primary_language(&filter), Some(rust));
+        let filter = StatsFilter {
+            categories: CategorySelection::default().with(LanguageCategory::Data, true),
+            ..filter
+        };
+        assert_eq!(stats.primary_language(&filter), Some(json));
+        let filter = StatsFilter {
+            categories: CategorySelection::default().with(LanguageCategory::Programming, false),
+            ..filter
+        };
+        assert_eq!(stats.primary_language(&filter), Some(Language::unknown()));
+        assert_eq!(DirStats::default().primary_language(&filter), None);
+    }
+
+    /// Tests that unrecognized files are grouped by extension or name.
+    #[test]
+    fn test_unknown_files() {
+        let registry = LanguageRegistry::builtin();
+        let rust = registry.find_by_alias("rust").unwrap();
+        let unknown = |lines| text_file(Language::unknown(), lines, FileTags::default());
+        let mut stats = DirStats::default();
+        stats.insert_file(
+            Path::new("main.rs"),
+            text_file(rust, 100, FileTags::default()),
+        );
+        stats.insert_file(Path::new("a.DAT"), unknown(1));
+        stats.insert_file(Path::new("data/b.dat"), unknown(2));
+        stats.insert_file(Path::new("data/CODEOWNERS"), unknown(3));
+        stats.tally_dir_stats();
+
+        let tallies = stats.tallies(&StatsFilter::default());
+        assert_eq!(
+            tallies.unknown.keys().collect::<Vec<_>>(),
+            vec![".dat", "CODEOWNERS"]
+        );
+        let dat = tallies.unknown[".dat"];
+        assert_eq!((dat.files, dat.lines, dat.bytes), (2, 3, 30));
+        assert_eq!(tallies.counts[&Language::unknown()].files, 3);
+        let data = &stats.dirs[&Name::from("data")];
+        assert_eq!(data.tallies(&StatsFilter::default()).unknown.len(), 2);
+    }
+
+    /// Tests that binary files are tallied as assets rather than languages.
+    #[test]
+    fn test_assets() {
+        let mut stats = DirStats::default();
+        stats.insert_file(
+            Path::new("img/logo.png"),
+            binary_file_stats(MediaType::Image, 1000),
+        );
+        stats.insert_file(
+            Path::new("img/icon.png"),
+            binary_file_stats(MediaType::Image, 24),
+        );
+        stats.insert_file(
+            Path::new("app.bin"),
+            binary_file_stats(MediaType::Executable, 5),
+        );
+        stats.tally_dir_stats();
+
+        let tallies = stats.tallies(&StatsFilter::default());
+        assert!(tallies.counts.is_empty());
+        assert!(tallies.unknown.is_empty());
+        assert_eq!(
+            tallies.assets[&MediaType::Image],
+            AssetCounts {
+                files: 2,
+                bytes: 1024
+            }
+        );
+        assert_eq!(tallies.assets[&MediaType::Executable].files, 1);
+        assert_eq!(stats.primary_language(&StatsFilter::default()), None);
+    }
+
+    /// Tests that tagged files are excluded unless the filter includes them.
+    #[test]
+    fn test_tagged_files() {
+        let registry = LanguageRegistry::builtin();
+        let go = registry.find_by_alias("go").unwrap();
+        let generated = FileTags {
+            generated: true,
+            ..FileTags::default()
+        };
+        let vendored = FileTags {
+            vendored: true,
+            ..FileTags::default()
+        };
+        let mut stats = DirStats::default();
+        stats.insert_file(
+            Path::new("main.go"),
+            text_file(go.clone(), 10, FileTags::default()),
+        );
+        stats.insert_file(
+            Path::new("api/service.pb.go"),
+            text_file(go.clone(), 1000, generated),
+        );
+        stats.insert_file(
+            Path::new("vendor/lib.go"),
+            text_file(go.clone(), 100, vendored),
+        );
+        stats.tally_dir_stats();
+
+        let lines = |filter: StatsFilter| stats.tallies(&filter).counts[&go].lines;
+        assert_eq!(lines(StatsFilter::default()), 10);
+        assert_eq!(
+            lines(StatsFilter {
+                generated: true,
+                ..StatsFilter::default()
+            }),
+            1010
+        );
+        assert_eq!(
+            lines(StatsFilter {
+                generated: true,
+                vendored: true,
+                ..StatsFilter::default()
+            }),
+            1110
+        );
+        assert_eq!(
+            stats.dirs[&Name::from("vendor")].primary_language(&StatsFilter::default()),
+            None
+        );
+
+        let generated_lines =
+            |filter: StatsFilter| stats.generated_tallies(&filter).counts[&go].lines;
+        assert_eq!(generated_lines(StatsFilter::default()), 1000);
+        assert_eq!(
+            generated_lines(StatsFilter {
+                generated: true,
+                ..StatsFilter::default()
+            }),
+            1000
+        );
+    }
+
+    /// Tests that snapshots are tallied without disturbing the statistics still
+    /// being collected.
+    #[test]
+    fn test_snapshot() {
+        let registry = LanguageRegistry::builtin();
+        let rust = registry.find_by_alias("rust").unwrap();
+        let root = Path::new("/repo");
+        let mut stats = CodeStats::new(root);
+        stats.insert_file(
+            &root.join("src/main.rs"),
+            text_file(rust.clone(), 10, FileTags::default()),
+        );
+
+        let snapshot = stats.snapshot();
+        assert_eq!(
+            snapshot.stats.tallies(&StatsFilter::default()).counts[&rust].files,
+            1
+        );
+
+        stats.insert_file(
+            &root.join("src/lib.rs"),
+            text_file(rust.clone(), 20, FileTags::default()),
+        );
+        stats.finish();
+        let counts = &stats.stats.tallies(&StatsFilter::default()).counts[&rust];
+        assert_eq!((counts.files, counts.lines), (2, 30));
+    }
+
+    /// Tests that patching a copy of the statistics only copies the
+    /// directories along the patched path.
+    #[test]
+    fn test_patch_copy() {
+        let registry = LanguageRegistry::builtin();
+        let rust = registry.find_by_alias("rust").unwrap();
+        let root = Path::new("/repo");
+        let mut stats = CodeStats::new(root);
+        stats.insert_file(
+            &root.join("src/main.rs"),
+            text_file(rust.clone(), 10, FileTags::default()),
+        );
+        stats.insert_file(
+            &root.join("tests/it.rs"),
+            text_file(rust.clone(), 5, FileTags::default()),
+        );
+        stats.finish();
+
+        let mut patched = stats.clone();
+        patched.apply_patch(StatsPatch::File(
+            root.join("src/lib.rs"),
+            text_file(rust.clone(), 20, FileTags::default()),
+        ));
+
+        let dir = |stats: &CodeStats, name: &str| stats.stats.dirs[&Name::from(name)].clone();
+        assert!(Arc::ptr_eq(&dir(&stats, "tests"), &dir(&patched, "tests")));
+        assert!(!Arc::ptr_eq(&dir(&stats, "src"), &dir(&patched, "src")));
+        assert_eq!(dir(&stats, "src").files.len(), 1);
+        assert_eq!(dir(&patched, "src").files.len(), 2);
+        let counts = &patched.stats.tallies(&StatsFilter::default()).counts[&rust];
+        assert_eq!((counts.files, counts.lines), (3, 35));
+    }
+
+    /// Tests that files are tallied by component.
+    #[test]
+    fn test_components() {
+        let registry = LanguageRegistry::builtin();
+        let go = registry.find_by_alias("go").unwrap();
+        let ts = registry.find_by_alias("typescript").unwrap();
+        let in_component = |language: Language, lines: usize, component: &str| FileStats {
+            component: Some(component.to_owned()),
+            ..text_file(language, lines, FileTags::default())
+        };
+        let mut stats = DirStats::default();
+        stats.insert_file(
+            Path::new("services/api/main.go"),
+            in_component(go.clone(), 100, "backend"),
+        );
+        stats.insert_file(
+            Path::new("services/db/db.go"),
+            in_component(go.clone(), 50, "backend"),
+        );
+        stats.insert_file(
+            Path::new("web/app.ts"),
+            in_component(ts.clone(), 30, "frontend"),
+        );
+        stats.insert_file(
+            Path::new("main.go"),
+            text_file(go.clone(), 5, FileTags::default()),
+        );
+        stats.tally_dir_stats();
+
+        let components = stats.tallies(&StatsFilter::default()).components;
+        assert_eq!(components.len(), 2);
+        assert_eq!(components["backend"].files, 2);
+        assert_eq!(components["backend"].lines, 150);
+        assert_eq!(components["frontend"].lines, 30);
+    }
+
+    /// Tests that paths that cannot be scanned are reported.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_scan_errors() {
+        let root = TestDir::new("collect");
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+        std::os::unix::fs::symlink(root.join("missing.rs"), root.join("link.rs")).unwrap();
+
+        let stats = collect_stats(
+            &root,
+            &ScanOptions {
+                follow_links: true,
+                ..scan_options()
+            },
+            &PathFilters::default(),
+            &CancellationToken::new(),
+            &progress_channel().0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.stats.files.len(), 1);
+        assert_eq!(stats.errors.len(), 1);
+        assert_eq!(stats.errors[0].path, root.join("link.rs"));
+        assert_eq!(stats.errors[0].kind, ScanErrorKind::BrokenSymlink);
+    }
+
+    /// Tests that symbolic links are skipped unless they are followed.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinks() {
+        let root = TestDir::new("symlinks");
+        let target = root.join("target");
+        let project = root.join("project");
+        fs::create_dir_all(&target).unwrap();
+        fs::create_dir_all(&project).unwrap();
+        fs::write(target.join("lib.rs"), "fn lib() {}\n").unwrap();
+        fs::write(project.join("main.rs"), "fn main() {}\n").unwrap();
+        std::os::unix::fs::symlink(&target, project.join("linked")).unwrap();
+        std::os::unix::fs::symlink(target.join("lib.rs"), project.join("link.rs")).unwrap();
+        std::os::unix::fs::symlink(root.join("missing.rs"), project.join("broken.rs")).unwrap();
+
+        let collect = |follow_links| {
+            let project = project.clone();
+
+            async move {
+                collect_stats(
+                    &project,
+                    &ScanOptions {
+                        follow_links,
+                        ..scan_options()
+                    },
+                    &PathFilters::default(),
+                    &CancellationToken::new(),
+                    &progress_channel().0,
+                )
                 .await
-                .unwrap();
+                .unwrap()
+            }
+        };
+
+        let stats = collect(false).await;
+        assert_eq!(stats.stats.files.len(), 1);
+        assert!(stats.stats.files.contains_key(&Name::from("main.rs")));
+        assert!(stats.stats.dirs.is_empty());
+        assert!(stats.errors.is_empty());
+
+        let stats = collect(true).await;
+        assert_eq!(stats.stats.files.len(), 2);
+        assert!(stats.stats.files.contains_key(&Name::from("link.rs")));
+        assert_eq!(stats.stats.dirs[&Name::from("linked")].files.len(), 1);
+        assert_eq!(stats.errors.len(), 1);
+    }
+
+    /// Tests that progress is published as files are counted.
+    #[tokio::test]
+    async fn test_progress() {
+        let root = TestDir::new("progress");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(root.join("src").join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("README.md"), "# Readme\n").unwrap();
+
+        let (progress_sender, progress_receiver) = progress_channel();
+        collect_stats(
+            &root,
+            &scan_options(),
+            &PathFilters::default(),
+            &CancellationToken::new(),
+            &progress_sender,
+        )
+        .await
+        .unwrap();
+
+        let progress = progress_receiver.borrow();
+        assert_eq!(progress.dirs, 2);
+        assert_eq!(progress.files, 2);
+        assert_eq!(progress.bytes, 22);
+        assert!(progress.current_path.is_some());
+    }
+
+    /// Tests that names that are not valid UTF-8 are kept and can be traversed.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_non_utf8_names() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let root = TestDir::new("non-utf8");
+        let dir_name = OsStr::from_bytes(b"caf\xe9");
+        fs::create_dir_all(root.join(dir_name)).unwrap();
+        fs::write(root.join(dir_name).join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join(OsStr::from_bytes(b"lib\xff.rs")), "fn lib() {}\n").unwrap();
+
+        let stats = collect_stats(
+            &root,
+            &scan_options(),
+            &PathFilters::default(),
+            &CancellationToken::new(),
+            &progress_channel().0,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.stats.files.len(), 1);
+        let (name, _) = stats.stats.files.iter().next().unwrap();
+        assert_eq!(name.to_string(), "lib\u{fffd}.rs");
+
+        let slice = stats.stats_slice(dir_name).unwrap();
+        assert_eq!(slice.files.len(), 1);
+    }
+
+    /// Tests that a cancelled scan stops and reports the cancellation.
+    #[tokio::test]
+    async fn test_cancelled() {
+        let root = TestDir::new("cancel");
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let err = collect_stats(
+            &root,
+            &scan_options(),
+            &PathFilters::default(),
+            &cancel,
+            &progress_channel().0,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ServiceError::Cancelled(path) if path == *root));
+    }
+
+    /// Generates a tree of source files to scan, with `dirs` directories of
+    /// `dirs` subdirectories each, every one holding `files` files.
+    fn generate_tree(root: &Path, dirs: usize, files: usize) {
+        let source =
+            "/// A generated function.\nfn generated() {\n    let x = 1;\n\n    // Comment.\n}\n"
+                .repeat(40);
+
+        for dir in 0..dirs {
+            for subdir in 0..dirs {
+                let dir_path = root.join(format!("dir{dir}")).join(format!("sub{subdir}"));
+                fs::create_dir_all(&dir_path).unwrap();
+
+                for file in 0..files {
+                    fs::write(dir_path.join(format!("file{file}.rs")), &source).unwrap();
+                }
+            }
         }
     }
 
-    drop(stats_sender);
-    pool.finish().await;
+    /// Scans a directory with the given number of walker threads.
+    async fn scan_with_threads(root: &Path, threads: usize) -> CodeStats {
+        let options = ScanOptions {
+            threads,
+            ..scan_options()
+        };
+
+        collect_stats(
+            root,
+            &options,
+            &PathFilters::default(),
+            &CancellationToken::new(),
+            &progress_channel().0,
+        )
+        .await
+        .unwrap()
+    }
 
-    let stats = stats_collection_task.await.unwrap();
+    /// Tests that scanning with many walker threads gives the same result as
+    /// scanning with one.
+    #[tokio::test]
+    async fn test_parallel_scan() {
+        let root = TestDir::new("parallel");
+        generate_tree(&root, 4, 8);
+        fs::write(
+            root.join("dir0").join(PROJECT_CONFIG_FILE_NAME),
+            "exclude = [\"sub1\"]\n",
+        )
+        .unwrap();
+        fs::write(
+            root.join("dir1").join(PROJECT_CONFIG_FILE_NAME),
+            "categories = []\n",
+        )
+        .unwrap();
 
-    Ok(stats)
+        let sequential = scan_with_threads(&root, 1).await;
+        let parallel = scan_with_threads(&root, 8).await;
+        assert_eq!(parallel, sequential);
+        let files = parallel
+            .stats
+            .tallies(&StatsFilter::default())
+            .counts
+            .values()
+            .map(|counts| counts.files)
+            .sum::<usize>();
+        assert_eq!(files, 4 * 4 * 8 - 8);
+        assert_eq!(parallel.config.files.len(), 2);
+    }
+
+    /// Benchmarks scanning a generated tree with one walker thread and with
+    /// one per CPU, printing the throughput of each. Run with
+    /// `make bench`.
+    #[tokio::test(flavor = "multi_thread")]
+    #[ignore]
+    async fn bench_collect_stats() {
+        let root = TestDir::new("bench");
+        generate_tree(&root, 40, 25);
+
+        for threads in [1, 0] {
+            let mut best = Duration::MAX;
+            let mut stats = None;
+
+            for _ in 0..3 {
+                let started = Instant::now();
+                stats = Some(scan_with_threads(&root, threads).await);
+                best = best.min(started.elapsed());
+            }
+
+            let tallies = stats.unwrap().stats.tallies(&StatsFilter::default());
+            let files = tallies
+                .counts
+                .values()
+                .map(|counts| counts.files)
+                .sum::<usize>();
+            let bytes = tallies
+                .counts
+                .values()
+                .map(|counts| counts.bytes)
+                .sum::<usize>();
+            let seconds = best.as_secs_f64();
+            println!(
+                "threads = {threads}: {files} files in {seconds:.3}s, {:.0} files/s, {}/s",
+                files as f64 / seconds,
+                format_bytes(bytes as f64 / seconds)
+            );
+        }
+    }
+
+    /// Tests that large files are counted in chunks, and only in part when
+    /// they exceed the read limit.
+    #[test]
+    fn test_large_file_stats() {
+        let root = TestDir::new("large");
+        let path = root.join("large.rs");
+        let line = "let x = 1; // a line of code\r\n";
+        let lines = 3 * READ_CHUNK_BYTES / line.len();
+        fs::write(&path, format!("/*\n{}*/\n", line.repeat(lines))).unwrap();
+        let size = fs::metadata(&path).unwrap().len() as usize;
+
+        let registry = LanguageRegistry::builtin();
+        let stats = file_stats(&registry, &path, None, &ScanOptions::default()).unwrap();
+        assert_eq!(stats.language.name(), "Rust");
+        assert_eq!(
+            stats.counts,
+            FileCounts {
+                lines: lines + 2,
+                comments: lines + 2,
+                bytes: size,
+                ..FileCounts::default()
+            }
+        );
+
+        let limit = 10 * line.len() + 3;
+        let options = ScanOptions {
+            read_limit: Some(limit as u64),
+            ..ScanOptions::default()
+        };
+        let stats = file_stats(&registry, &path, None, &options).unwrap();
+        assert_eq!(
+            stats.counts,
+            FileCounts {
+                lines: 11,
+                comments: 11,
+                bytes: size,
+                partial: true,
+                ..FileCounts::default()
+            }
+        );
+
+        let options = ScanOptions {
+            read_limit: Some(size as u64),
+            ..ScanOptions::default()
+        };
+        let stats = file_stats(&registry, &path, None, &options).unwrap();
+        assert!(!stats.counts.partial);
+
+        let options = ScanOptions {
+            read_limit: Some(0),
+            ..ScanOptions::default()
+        };
+        let stats = file_stats(&registry, &path, None, &options).unwrap();
+        assert!(!stats.counts.partial);
+        assert_eq!(stats.counts.lines, lines + 2);
+    }
+
+    /// Tests that files with byte order marks are decoded before their lines
+    /// are counted, and that line feeds can be counted like `wc -l`.
+    #[test]
+    fn test_encodings() {
+        let root = TestDir::new("encodings");
+        let registry = LanguageRegistry::builtin();
+        let source = "// Comment\r\nclass A {}\r\n\r\nclass B {}";
+
+        let utf16 = [0xff, 0xfe]
+            .into_iter()
+            .chain(source.encode_utf16().flat_map(u16::to_le_bytes))
+            .collect::<Vec<_>>();
+        fs::write(root.join("utf16.cs"), &utf16).unwrap();
+        fs::write(root.join("bom.sh"), "\u{feff}#!/bin/sh\necho hi\n").unwrap();
+        fs::write(
+            root.join("latin1.txt"),
+            b"Le caf\xe9 est tr\xe8s bon aujourd'hui.\n",
+        )
+        .unwrap();
+
+        let stats = file_stats(
+            &registry,
+            root.join("utf16.cs"),
+            None,
+            &ScanOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(stats.encoding, Some(TextEncoding::Utf16Le));
+        assert_eq!(
+            stats.counts,
+            FileCounts {
+                lines: 4,
+                code: 2,
+                comments: 1,
+                blanks: 1,
+                bytes: utf16.len(),
+                ..FileCounts::default()
+            }
+        );
+
+        let newlines = ScanOptions {
+            line_count_mode: LineCountMode::Newlines,
+            ..ScanOptions::default()
+        };
+        let stats = file_stats(&registry, root.join("utf16.cs"), None, &newlines).unwrap();
+        assert_eq!(stats.counts.lines, 3);
+
+        let stats = file_stats(
+            &registry,
+            root.join("bom.sh"),
+            None,
+            &ScanOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(stats.encoding, Some(TextEncoding::Utf8Bom));
+        assert_eq!(stats.language.name(), "Shell");
+        assert_eq!(stats.counts.lines, 2);
+
+        let stats = file_stats(
+            &registry,
+            root.join("latin1.txt"),
+            None,
+            &ScanOptions::default(),
+        )
+        .unwrap();
+        assert_eq!(stats.encoding, Some(TextEncoding::Other));
+        assert_eq!(stats.counts.lines, 1);
+    }
+
+    /// Tests that line ending and whitespace styles are detected per file,
+    /// tallied per directory and language, and that offending files are
+    /// listed.
+    #[tokio::test]
+    async fn test_style_stats() {
+        let root = TestDir::new("style");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::write(
+            root.join("src/good.rs"),
+            "fn a() {\n    if b {\n        c();\n    }\n}\n",
+        )
+        .unwrap();
+        fs::write(root.join("src/crlf.rs"), "fn a() {\r\n    b();\r\n}\r\n").unwrap();
+        fs::write(root.join("tabs.rs"), "fn a() {\n\tb();  \n}").unwrap();
+        fs::write(root.join("main.go"), "func main() {\n\tb()\n}\n").unwrap();
+
+        let stats = collect_stats(
+            &root,
+            &scan_options(),
+            &PathFilters::default(),
+            &CancellationToken::new(),
+            &progress_channel().0,
+        )
+        .await
+        .unwrap()
+        .stats;
+        let filter = StatsFilter::default();
+        let registry = LanguageRegistry::builtin();
+        let rust = registry.find_by_alias("rust").unwrap();
+        let go = registry.find_by_alias("go").unwrap();
+
+        let good = stats.dirs[&Name::from(OsStr::new("src"))].files
+            [&Name::from(OsStr::new("good.rs"))]
+            .style
+            .unwrap();
+        assert_eq!(good.line_endings(), LineEndingStyle::Lf);
+        assert_eq!(good.indentation(), IndentStyle::Spaces);
+        assert_eq!(good.indent_width, Some(4));
+        assert!(!good.has_issues(rust.indentation()));
+
+        let style = stats.tallies(&filter).counts[&rust].style;
+        assert_eq!(
+            style,
+            StyleCounts {
+                lf_files: 2,
+                crlf_files: 1,
+                tab_indented_files: 1,
+                space_indented_files: 2,
+                unexpected_indentation_files: 1,
+                trailing_whitespace_files: 1,
+                trailing_whitespace_lines: 1,
+                missing_final_newline_files: 1,
+                files_with_issues: 2,
+                ..StyleCounts::default()
+            }
+        );
+
+        let go_style = stats.tallies(&filter).counts[&go].style;
+        assert_eq!(go_style.tab_indented_files, 1);
+        assert_eq!(go_style.files_with_issues, 0);
+
+        let src_style = stats.dirs[&Name::from(OsStr::new("src"))]
+            .tallies(&filter)
+            .counts[&rust]
+            .style;
+        assert_eq!(src_style.non_lf_files(), 1);
+        assert_eq!(src_style.files_with_issues, 1);
+
+        let offenders = stats
+            .style_offenders(&filter)
+            .into_iter()
+            .map(|(path, _)| path)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            offenders,
+            vec![PathBuf::from("src/crlf.rs"), PathBuf::from("tabs.rs")]
+        );
+    }
+
+    /// Tests that a scan of a missing directory fails with the root's path.
+    #[tokio::test]
+    async fn test_unreadable_root() {
+        let root = Path::new("/nonexistent/code-stats-root");
+        let err = collect_stats(
+            root,
+            &scan_options(),
+            &PathFilters::default(),
+            &CancellationToken::new(),
+            &progress_channel().0,
+        )
+        .await
+        .unwrap_err();
+        assert!(matches!(err, ServiceError::UnreadableRoot { path, .. } if path == root));
+
+        let stats = CodeStats::new(root);
+        assert!(matches!(
+            stats.stats_slice("src"),
+            Err(ServiceError::InvalidSubpath(path)) if path == Path::new("src")
+        ));
+    }
 }
@@ -0,0 +1,188 @@
+//! Language detection services.
+
+use super::{disambiguate, Language, LanguageRegistry};
+use std::path::Path;
+
+/// The number of lines at the start and end of a file searched for modelines.
+const MODELINE_SEARCH_LINES: usize = 5;
+
+/// The markers that introduce a vim modeline.
+const VIM_MODELINE_MARKERS: [&str; 3] = ["vim:", "vi:", "ex:"];
+
+/// The vim options that set the language of a file.
+const VIM_LANGUAGE_OPTIONS: [&str; 4] = ["ft", "filetype", "syn", "syntax"];
+
+/// The marker that surrounds an emacs modeline.
+const EMACS_MODELINE_MARKER: &str = "-*-";
+
+/// Detects a language from the exact name of a file.
+fn detect_by_file_name(registry: &LanguageRegistry, path: &Path) -> Option<Language> {
+    registry.find_by_filename(path.file_name()?.to_str()?)
+}
+
+/// Detects a language from a shebang line, following `env` indirection.
+fn detect_by_shebang(registry: &LanguageRegistry, content: &[u8]) -> Option<Language> {
+    let first_line = content.split(|c| *c == b'\n').next()?;
+    let shebang = std::str::from_utf8(first_line.strip_prefix(b"#!")?).ok()?;
+    let mut args = shebang.split_whitespace();
+    let mut interpreter = interpreter_name(args.next()?);
+
+    if interpreter == "env" {
+        interpreter = args
+            .find(|arg| !arg.starts_with('-') && !arg.contains('='))
+            .map(interpreter_name)?;
+    }
+
+    registry.find_by_interpreter(interpreter)
+}
+
+/// Gets the file name portion of an interpreter path.
+fn interpreter_name(path: &str) -> &str {
+    path.rsplit('/').next().unwrap_or(path)
+}
+
+/// Detects a language from a vim or emacs modeline near the start or end of
+/// the file.
+fn detect_by_modeline(registry: &LanguageRegistry, content: &[u8]) -> Option<Language> {
+    let lines = content.split(|c| *c == b'\n').collect::<Vec<_>>();
+    let head = lines.iter().take(MODELINE_SEARCH_LINES);
+    let tail = lines
+        .iter()
+        .skip(MODELINE_SEARCH_LINES)
+        .rev()
+        .take(MODELINE_SEARCH_LINES);
+
+    head.chain(tail)
+        .filter_map(|line| std::str::from_utf8(line).ok())
+        .find_map(|line| {
+            vim_modeline_language(registry, line)
+                .or_else(|| emacs_modeline_language(registry, line))
+        })
+}
+
+/// Gets the language set by a vim modeline, such as `vim: set ft=python:`.
+fn vim_modeline_language(registry: &LanguageRegistry, line: &str) -> Option<Language> {
+    let options = VIM_MODELINE_MARKERS.iter().find_map(|marker| {
+        line.match_indices(marker)
+            .find(|(idx, _)| {
+                line[..*idx]
+                    .chars()
+                    .next_back()
+                    .is_none_or(char::is_whitespace)
+            })
+            .map(|(idx, _)| &line[idx + marker.len()..])
+    })?;
+
+    options
+        .split(|c: char| c.is_whitespace() || c == ':')
+        .filter_map(|option| option.split_once('='))
+        .find(|(key, _)| VIM_LANGUAGE_OPTIONS.contains(key))
+        .and_then(|(_, value)| registry.find_by_alias(value))
+}
+
+/// Gets the language set by an emacs modeline, such as `-*- mode: ruby -*-`
+/// or `-*- ruby -*-`.
+fn emacs_modeline_language(registry: &LanguageRegistry, line: &str) -> Option<Language> {
+    let start = line.find(EMACS_MODELINE_MARKER)? + EMACS_MODELINE_MARKER.len();
+    let end = start + line[start..].find(EMACS_MODELINE_MARKER)?;
+    let variables = line[start..end].trim();
+
+    let mode = if variables.contains(':') {
+        variables.split(';').find_map(|variable| {
+            let (key, value) = variable.split_once(':')?;
+            key.trim()
+                .eq_ignore_ascii_case("mode")
+                .then_some(value.trim())
+        })?
+    } else {
+        variables
+    };
+
+    registry.find_by_alias(mode.strip_suffix("-mode").unwrap_or(mode))
+}
+
+/// Detects a language from the extension of a file, running content
+/// heuristics when the extension is shared by several languages.
+fn detect_by_extension(
+    registry: &LanguageRegistry,
+    path: &Path,
+    content: &[u8],
+) -> Option<Language> {
+    let ext = path.extension()?.to_str()?.to_lowercase();
+
+    match registry.extension_candidates(&ext) {
+        [] => None,
+        [language] => Some(language.clone()),
+        candidates => Some(disambiguate(&ext, candidates, content)),
+    }
+}
+
+/// Detects the language of a file given its path and contents, using the
+/// languages in the given registry. The exact
+/// file name is checked first, followed by a shebang line, a vim or emacs
+/// modeline, and finally the file extension, disambiguated by content when
+/// necessary.
+pub fn detect_language(registry: &LanguageRegistry, path: &Path, content: &[u8]) -> Language {
+    detect_by_file_name(registry, path)
+        .or_else(|| detect_by_shebang(registry, content))
+        .or_else(|| detect_by_modeline(registry, content))
+        .or_else(|| detect_by_extension(registry, path, content))
+        .unwrap_or_else(Language::unknown)
+}
+
+/// Language detection tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Detects the language of a file with the given name and contents,
+    /// returning the name of the language.
+    fn detect(name: &str, content: &str) -> String {
+        let registry = LanguageRegistry::builtin();
+        detect_language(&registry, Path::new(name), content.as_bytes())
+            .name()
+            .to_owned()
+    }
+
+    /// Tests detection from exact file names.
+    #[test]
+    fn test_file_names() {
+        assert_eq!(detect("Makefile", "all:\n"), "Makefile");
+        assert_eq!(detect("Dockerfile", "FROM alpine\n"), "Dockerfile");
+        assert_eq!(detect("CMakeLists.txt", ""), "CMake");
+        assert_eq!(detect(".bashrc", "alias ll='ls -l'\n"), "Shell");
+    }
+
+    /// Tests detection from shebang lines.
+    #[test]
+    fn test_shebangs() {
+        assert_eq!(detect("deploy", "#!/bin/bash\necho hi\n"), "Shell");
+        assert_eq!(detect("run", "#!/usr/bin/env python3\nprint()\n"), "Python");
+        assert_eq!(
+            detect("run", "#!/usr/bin/env -S FOO=1 node --trace\n"),
+            "JavaScript"
+        );
+        assert_eq!(detect("tool", "#!/usr/bin/python3.11 -u\n"), "Python");
+        assert_eq!(detect("script.txt", "#!/usr/bin/ruby\n"), "Ruby");
+        assert_eq!(detect("unknown", "#!/usr/bin/env foo\n"), "Other");
+    }
+
+    /// Tests detection from vim and emacs modelines.
+    #[test]
+    fn test_modelines() {
+        assert_eq!(detect("build", "echo hi\n# vim: set ft=sh :\n"), "Shell");
+        assert_eq!(detect("hook", "# vi:filetype=python\nprint()\n"), "Python");
+        assert_eq!(detect("tasks", "# -*- mode: ruby -*-\n"), "Ruby");
+        assert_eq!(detect("config", "// -*- C++ -*-\n"), "C++");
+        assert_eq!(detect("notes", "savim: ft=python\n"), "Other");
+    }
+
+    /// Tests that extensions are used as a fallback.
+    #[test]
+    fn test_extensions() {
+        assert_eq!(detect("main.rs", "fn main() {}\n"), "Rust");
+        assert_eq!(detect("MAIN.C", "int main;\n"), "C");
+        assert_eq!(detect("main.py", "#!/bin/sh\n"), "Shell");
+        assert_eq!(detect("README", "text\n"), "Other");
+    }
+}
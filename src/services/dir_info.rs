@@ -1,7 +1,8 @@
 //! Directory information services.
 
+use super::{ServiceError, ServiceResult};
 use std::env;
-use std::io;
+use std::ffi::OsString;
 use std::path::{Path, PathBuf};
 use sysinfo::Disks;
 use tokio::fs;
@@ -12,29 +13,35 @@ use tokio::task::spawn_blocking;
 pub struct DirectoryInfo {
     /// The directory's path.
     pub path: PathBuf,
-    /// The list of subdirectories.
-    pub dirs: Vec<String>,
-    /// The list of files within the directory.
-    pub files: Vec<String>,
+    /// The names of the subdirectories, which need not be valid UTF-8.
+    pub dirs: Vec<OsString>,
+    /// The names of the files within the directory, which need not be valid
+    /// UTF-8.
+    pub files: Vec<OsString>,
 }
 
 /// Gets information on a directory that isn't at the root of the filesystem.
-async fn get_non_root_dir_info<P>(path: P) -> io::Result<DirectoryInfo>
+async fn get_non_root_dir_info<P>(path: P) -> ServiceResult<DirectoryInfo>
 where
     P: AsRef<Path>,
 {
     let mut dirs = vec![];
     let mut files = vec![];
 
-    let mut entries = fs::read_dir(&path).await?;
+    let unreadable = |source| ServiceError::UnreadableDirectory {
+        path: path.as_ref().to_path_buf(),
+        source,
+    };
+    let mut entries = fs::read_dir(&path).await.map_err(unreadable)?;
 
-    while let Some(entry) = entries.next_entry().await? {
-        let file_type = entry.file_type().await?;
+    while let Some(entry) = entries.next_entry().await.map_err(unreadable)? {
+        let file_type = entry.file_type().await.map_err(unreadable)?;
+        let name = entry.file_name();
 
         if file_type.is_dir() {
-            dirs.push(entry.file_name().to_str().unwrap().to_owned());
+            dirs.push(name);
         } else if file_type.is_file() {
-            files.push(entry.file_name().to_str().unwrap().to_owned());
+            files.push(name);
         }
     }
 
@@ -46,13 +53,13 @@ where
 }
 
 /// Gets information on the directory at the root of the filesystem.
-async fn get_root_dir_info() -> io::Result<DirectoryInfo> {
+async fn get_root_dir_info() -> ServiceResult<DirectoryInfo> {
     match env::consts::OS {
         "windows" => {
             let disks_handle = spawn_blocking(|| {
                 Disks::new_with_refreshed_list()
                     .into_iter()
-                    .map(|disk| disk.mount_point().to_str().unwrap().to_owned())
+                    .map(|disk| disk.mount_point().as_os_str().to_owned())
                     .collect::<Vec<_>>()
             });
             let mut disks = disks_handle.await.unwrap();
@@ -69,18 +76,18 @@ async fn get_root_dir_info() -> io::Result<DirectoryInfo> {
 }
 
 /// Gets the directory information for any given path.
-async fn collect_dir_info<P>(path: P) -> io::Result<DirectoryInfo>
+async fn collect_dir_info<P>(path: P) -> ServiceResult<DirectoryInfo>
 where
     P: AsRef<Path>,
 {
-    match path.as_ref().to_str().unwrap() {
-        "" | "/" => get_root_dir_info().await,
+    match path.as_ref().to_str() {
+        Some("" | "/") => get_root_dir_info().await,
         _ => get_non_root_dir_info(path).await,
     }
 }
 
 /// Gets information on a directory.
-pub async fn get_directory_info<P>(path: P) -> io::Result<DirectoryInfo>
+pub async fn get_directory_info<P>(path: P) -> ServiceResult<DirectoryInfo>
 where
     P: AsRef<Path>,
 {
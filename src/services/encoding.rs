@@ -0,0 +1,218 @@
+//! Text encoding detection and decoding.
+
+use super::invalid_utf8_bytes;
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::char::REPLACEMENT_CHARACTER;
+
+/// The byte order mark of UTF-8 text.
+const UTF8_BOM: &[u8] = b"\xef\xbb\xbf";
+
+/// The byte order mark of little-endian UTF-16 text.
+const UTF16LE_BOM: &[u8] = b"\xff\xfe";
+
+/// The byte order mark of big-endian UTF-16 text.
+const UTF16BE_BOM: &[u8] = b"\xfe\xff";
+
+/// The range of UTF-16 code units that begin a surrogate pair.
+const HIGH_SURROGATES: std::ops::Range<u16> = 0xd800..0xdc00;
+
+/// The encoding of a text file.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Serialize, Deserialize,
+)]
+pub enum TextEncoding {
+    /// UTF-8 without a byte order mark, which includes plain ASCII.
+    #[default]
+    Utf8,
+    /// UTF-8 with a byte order mark.
+    Utf8Bom,
+    /// Little-endian UTF-16, marked by a byte order mark.
+    Utf16Le,
+    /// Big-endian UTF-16, marked by a byte order mark.
+    Utf16Be,
+    /// Text that is not valid UTF-8, such as text in a legacy 8-bit encoding.
+    /// Its lines are counted from its bytes as they are.
+    Other,
+}
+
+impl TextEncoding {
+    /// Detects the encoding of a file from the start of its content.
+    pub fn detect(head: &[u8]) -> Self {
+        if head.starts_with(UTF8_BOM) {
+            Self::Utf8Bom
+        } else if head.starts_with(UTF16LE_BOM) {
+            Self::Utf16Le
+        } else if head.starts_with(UTF16BE_BOM) {
+            Self::Utf16Be
+        } else if invalid_utf8_bytes(head) > 0 {
+            Self::Other
+        } else {
+            Self::Utf8
+        }
+    }
+
+    /// Gets the name of the encoding.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Utf8 => "UTF-8",
+            Self::Utf8Bom => "UTF-8 with BOM",
+            Self::Utf16Le => "UTF-16LE",
+            Self::Utf16Be => "UTF-16BE",
+            Self::Other => "Non-UTF-8",
+        }
+    }
+
+    /// Gets the byte order mark that starts text in this encoding, which is
+    /// empty for encodings without one.
+    pub fn bom(&self) -> &'static [u8] {
+        match self {
+            Self::Utf8Bom => UTF8_BOM,
+            Self::Utf16Le => UTF16LE_BOM,
+            Self::Utf16Be => UTF16BE_BOM,
+            Self::Utf8 | Self::Other => b"",
+        }
+    }
+
+    /// Decodes a sample of text to UTF-8, without its byte order mark.
+    /// Invalid UTF-16 is replaced with the replacement character.
+    pub fn decode_sample<'a>(&self, sample: &'a [u8]) -> Cow<'a, [u8]> {
+        let sample = sample.strip_prefix(self.bom()).unwrap_or(sample);
+        let mut decoder = TextDecoder::new(*self);
+        let text = decoder.decode(sample);
+
+        match decoder.finish() {
+            Some(rest) => Cow::Owned([&*text, &rest].concat()),
+            None => text,
+        }
+    }
+}
+
+/// Decodes text to UTF-8 as it is read in chunks, carrying code units split
+/// between chunks over to the next. Text in encodings other than UTF-16 is
+/// passed through as it is.
+#[derive(Debug, Clone)]
+pub struct TextDecoder {
+    /// The encoding of the text.
+    encoding: TextEncoding,
+    /// The bytes of an incomplete code unit or surrogate pair at the end of
+    /// the previous chunk.
+    pending: Vec<u8>,
+}
+
+impl TextDecoder {
+    /// Creates a decoder for text in the given encoding, after its byte order
+    /// mark.
+    pub fn new(encoding: TextEncoding) -> Self {
+        Self {
+            encoding,
+            pending: Vec::new(),
+        }
+    }
+
+    /// Decodes the next chunk of text.
+    pub fn decode<'a>(&mut self, chunk: &'a [u8]) -> Cow<'a, [u8]> {
+        let from_bytes = match self.encoding {
+            TextEncoding::Utf16Le => u16::from_le_bytes,
+            TextEncoding::Utf16Be => u16::from_be_bytes,
+            _ => return Cow::Borrowed(chunk),
+        };
+
+        self.pending.extend_from_slice(chunk);
+        let mut units = self
+            .pending
+            .chunks_exact(2)
+            .map(|pair| from_bytes([pair[0], pair[1]]))
+            .collect::<Vec<_>>();
+
+        // A surrogate pair may be completed by the next chunk.
+        if units
+            .last()
+            .is_some_and(|unit| HIGH_SURROGATES.contains(unit))
+        {
+            units.pop();
+        }
+
+        self.pending.drain(..units.len() * 2);
+        let text = char::decode_utf16(units)
+            .map(|c| c.unwrap_or(REPLACEMENT_CHARACTER))
+            .collect::<String>();
+
+        Cow::Owned(text.into_bytes())
+    }
+
+    /// Decodes whatever incomplete text remains at the end of the input, if
+    /// any, as a replacement character.
+    pub fn finish(&mut self) -> Option<Vec<u8>> {
+        (!self.pending.is_empty()).then(|| {
+            self.pending.clear();
+            REPLACEMENT_CHARACTER.to_string().into_bytes()
+        })
+    }
+}
+
+/// Text encoding tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Encodes text as UTF-16 with a byte order mark.
+    fn utf16(text: &str, big_endian: bool) -> Vec<u8> {
+        let bom = if big_endian { UTF16BE_BOM } else { UTF16LE_BOM };
+        let units = text.encode_utf16().flat_map(|unit| {
+            if big_endian {
+                unit.to_be_bytes()
+            } else {
+                unit.to_le_bytes()
+            }
+        });
+
+        bom.iter().copied().chain(units).collect()
+    }
+
+    /// Tests detection from byte order marks and UTF-8 validity.
+    #[test]
+    fn test_detect() {
+        assert_eq!(TextEncoding::detect(b"fn main() {}\n"), TextEncoding::Utf8);
+        assert_eq!(TextEncoding::detect(b""), TextEncoding::Utf8);
+        assert_eq!(
+            TextEncoding::detect(b"\xef\xbb\xbfusing System;"),
+            TextEncoding::Utf8Bom
+        );
+        assert_eq!(
+            TextEncoding::detect(&utf16("x", false)),
+            TextEncoding::Utf16Le
+        );
+        assert_eq!(
+            TextEncoding::detect(&utf16("x", true)),
+            TextEncoding::Utf16Be
+        );
+        assert_eq!(TextEncoding::detect(b"caf\xe9\n"), TextEncoding::Other);
+    }
+
+    /// Tests that UTF-16 is decoded however it is split into chunks.
+    #[test]
+    fn test_decode_chunks() {
+        let text = "// caf\u{e9} \u{1f980}\r\nint x;\n";
+
+        for big_endian in [false, true] {
+            let bytes = utf16(text, big_endian);
+            let encoding = TextEncoding::detect(&bytes);
+            assert_eq!(&*encoding.decode_sample(&bytes), text.as_bytes());
+
+            for chunk_size in 1..bytes.len() {
+                let mut decoder = TextDecoder::new(encoding);
+                let decoded = bytes[encoding.bom().len()..]
+                    .chunks(chunk_size)
+                    .flat_map(|chunk| decoder.decode(chunk).into_owned())
+                    .collect::<Vec<_>>();
+                assert_eq!(decoder.finish(), None);
+                assert_eq!(decoded, text.as_bytes());
+            }
+        }
+
+        let mut decoder = TextDecoder::new(TextEncoding::Utf16Le);
+        assert_eq!(&*decoder.decode(b"a\x00b"), b"a");
+        assert_eq!(decoder.finish(), Some("\u{fffd}".as_bytes().to_vec()));
+    }
+}
@@ -0,0 +1,99 @@
+//! Errors returned by services.
+
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::path::PathBuf;
+
+/// An error returned by a service, carrying the path it concerns.
+#[derive(Debug)]
+pub enum ServiceError {
+    /// The subpath does not exist within the collected statistics.
+    InvalidSubpath(PathBuf),
+    /// The root directory of a scan could not be read.
+    UnreadableRoot {
+        /// The root directory.
+        path: PathBuf,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// A directory could not be listed.
+    UnreadableDirectory {
+        /// The directory.
+        path: PathBuf,
+        /// The underlying error.
+        source: io::Error,
+    },
+    /// The scan of a directory was cancelled.
+    Cancelled(PathBuf),
+    /// The configuration used to scan a directory is invalid.
+    Config {
+        /// The directory the configuration applies to.
+        path: PathBuf,
+        /// A description of the problem.
+        message: String,
+    },
+}
+
+impl fmt::Display for ServiceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSubpath(path) => write!(
+                f,
+                "{} is not a directory within the statistics",
+                path.display()
+            ),
+            Self::UnreadableRoot { path, source } => {
+                write!(f, "cannot read {}: {}", path.display(), source)
+            }
+            Self::UnreadableDirectory { path, source } => {
+                write!(f, "cannot list {}: {}", path.display(), source)
+            }
+            Self::Cancelled(path) => write!(f, "the scan of {} was cancelled", path.display()),
+            Self::Config { path, message } => write!(
+                f,
+                "invalid configuration for {}: {}",
+                path.display(),
+                message
+            ),
+        }
+    }
+}
+
+impl Error for ServiceError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            Self::UnreadableRoot { source, .. } | Self::UnreadableDirectory { source, .. } => {
+                Some(source)
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The result of a service.
+pub type ServiceResult<T> = Result<T, ServiceError>;
+
+/// Service error tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests that errors describe their path and underlying error.
+    #[test]
+    fn test_service_error() {
+        let err = ServiceError::UnreadableRoot {
+            path: PathBuf::from("/repo"),
+            source: io::Error::from(io::ErrorKind::PermissionDenied),
+        };
+        assert!(err.to_string().starts_with("cannot read /repo: "));
+        assert!(err.source().is_some());
+
+        let err = ServiceError::InvalidSubpath(PathBuf::from("src/missing"));
+        assert_eq!(
+            err.to_string(),
+            "src/missing is not a directory within the statistics"
+        );
+        assert!(err.source().is_none());
+    }
+}
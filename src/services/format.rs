@@ -15,3 +15,26 @@ pub fn format_with_commas(num: usize) -> String {
 
     num_formatted
 }
+
+/// Formats a number of bytes using binary units, such as `1.5 MiB`.
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["KiB", "MiB", "GiB", "TiB", "PiB"];
+
+    if bytes < 1024.0 {
+        return format!("{} B", bytes.round());
+    }
+
+    let mut value = bytes / 1024.0;
+    let mut unit = UNITS[0];
+
+    for next_unit in &UNITS[1..] {
+        if value < 1024.0 {
+            break;
+        }
+
+        value /= 1024.0;
+        unit = next_unit;
+    }
+
+    format!("{:.1} {}", value, unit)
+}
@@ -0,0 +1,178 @@
+//! Heuristics for detecting generated, minified and lock files.
+
+use regex::bytes::Regex;
+use std::path::Path;
+use std::sync::OnceLock;
+
+/// The names of lock files written by package managers.
+const LOCK_FILE_NAMES: &[&str] = &[
+    "Cargo.lock",
+    "Gemfile.lock",
+    "Package.resolved",
+    "Pipfile.lock",
+    "Podfile.lock",
+    "bun.lock",
+    "composer.lock",
+    "deno.lock",
+    "flake.lock",
+    "go.sum",
+    "go.work.sum",
+    "gradle.lockfile",
+    "mix.lock",
+    "npm-shrinkwrap.json",
+    "package-lock.json",
+    "packages.lock.json",
+    "paket.lock",
+    "pdm.lock",
+    "pnpm-lock.yaml",
+    "poetry.lock",
+    "pubspec.lock",
+    "uv.lock",
+    "yarn.lock",
+];
+
+/// Suffixes of the names of minified files, source maps and files generated
+/// by common code generators.
+const GENERATED_FILE_SUFFIXES: &[&str] = &[
+    ".min.js",
+    "-min.js",
+    ".min.mjs",
+    ".min.css",
+    "-min.css",
+    ".js.map",
+    ".css.map",
+    ".pb.go",
+    ".pb.gw.go",
+    ".pb.cc",
+    ".pb.h",
+    "_pb2.py",
+    "_pb2.pyi",
+    "_pb2_grpc.py",
+    "_pb.js",
+    "_pb.d.ts",
+    "_grpc_pb.js",
+    ".pb.swift",
+    ".g.dart",
+    ".freezed.dart",
+    ".pbobjc.h",
+    ".pbobjc.m",
+    ".designer.cs",
+    ".g.cs",
+    ".generated.cs",
+    "_generated.go",
+];
+
+/// The number of lines at the start of a file searched for generated code
+/// markers.
+const GENERATED_MARKER_SEARCH_LINES: usize = 5;
+
+/// The pattern matching markers that state a file is generated.
+/// Mere mentions of generated code, such as "returns autogenerated IDs", do
+/// not match.
+const GENERATED_MARKER_PATTERN: &str = r"(?i)code generated .*do not edit|@generated\b|<auto-generated\b|auto-?generated.*(do not|don't) (edit|modify)|(this|the following) (file|code) (is|was) (automatically |auto-?)?generated|generated by .*(do not|don't) (edit|modify)";
+
+/// The average line length, in bytes, above which content is considered
+/// minified.
+const MINIFIED_MIN_AVERAGE_LINE_LENGTH: usize = 500;
+
+/// The size, in bytes, below which content is never considered minified.
+const MINIFIED_MIN_BYTES: usize = 4 * 1024;
+
+/// Gets the compiled pattern matching generated code markers.
+fn generated_marker() -> &'static Regex {
+    static PATTERN: OnceLock<Regex> = OnceLock::new();
+
+    PATTERN.get_or_init(|| Regex::new(GENERATED_MARKER_PATTERN).unwrap())
+}
+
+/// Checks whether a file is a lock file or has a name typical of generated
+/// or minified files.
+fn is_generated_file_name(path: &Path) -> bool {
+    let Some(name) = path.file_name().and_then(|name| name.to_str()) else {
+        return false;
+    };
+
+    LOCK_FILE_NAMES.contains(&name)
+        || GENERATED_FILE_SUFFIXES
+            .iter()
+            .any(|suffix| name.len() > suffix.len() && name.ends_with(suffix))
+}
+
+/// Checks whether the first lines of the content mark it as generated.
+fn has_generated_marker(content: &[u8]) -> bool {
+    content
+        .split(|c| *c == b'\n')
+        .take(GENERATED_MARKER_SEARCH_LINES)
+        .any(|line| generated_marker().is_match(line))
+}
+
+/// Checks whether the content is minified, judging by its average line
+/// length.
+fn is_minified(content: &[u8]) -> bool {
+    let lines = content.split(|c| *c == b'\n').count();
+
+    content.len() >= MINIFIED_MIN_BYTES && content.len() / lines > MINIFIED_MIN_AVERAGE_LINE_LENGTH
+}
+
+/// Checks whether a file is generated, minified or a lock file, given its
+/// path and content.
+pub fn is_generated(path: &Path, content: &[u8]) -> bool {
+    is_generated_file_name(path) || has_generated_marker(content) || is_minified(content)
+}
+
+/// Generated file detection tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests detection from file names.
+    #[test]
+    fn test_file_names() {
+        let generated = |name| is_generated(Path::new(name), b"");
+        assert!(generated("web/package-lock.json"));
+        assert!(generated("Cargo.lock"));
+        assert!(generated("static/app.min.js"));
+        assert!(generated("api/service.pb.go"));
+        assert!(generated("proto/service_pb2.py"));
+        assert!(!generated("package.json"));
+        assert!(!generated("main.go"));
+        assert!(!generated(".min.js"));
+    }
+
+    /// Tests detection from header markers.
+    #[test]
+    fn test_markers() {
+        let generated = |content: &str| is_generated(Path::new("file"), content.as_bytes());
+        assert!(generated(
+            "// Code generated by protoc-gen-go. DO NOT EDIT.\npackage api\n"
+        ));
+        assert!(generated("/*\n * @generated by some tool\n */\n"));
+        assert!(generated("# This file is automatically generated.\n"));
+        assert!(generated(
+            "// <auto-generated>\n//     This code was generated by a tool.\n"
+        ));
+        assert!(generated("// Auto-generated by sqlc. Do not edit.\n"));
+        assert!(generated("# This file is auto-generated\n"));
+        assert!(!generated("// Handles generated reports.\nfn main() {}\n"));
+        assert!(!generated(
+            "// Returns autogenerated IDs for new rows.\nfn next_id() {}\n"
+        ));
+        assert!(!generated(
+            "/* Keys are auto-generated by the database. */\n"
+        ));
+        assert!(!generated(
+            "1\n2\n3\n4\n5\n// Code generated by protoc-gen-go. DO NOT EDIT.\n"
+        ));
+    }
+
+    /// Tests detection of minified content.
+    #[test]
+    fn test_minified() {
+        let minified = "var a=1;".repeat(1000);
+        assert!(is_generated(Path::new("app.js"), minified.as_bytes()));
+
+        let source = "let value = compute(input);\n".repeat(1000);
+        assert!(!is_generated(Path::new("app.js"), source.as_bytes()));
+        assert!(!is_generated(Path::new("short.js"), b"var a=1;"));
+    }
+}
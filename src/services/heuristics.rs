@@ -0,0 +1,261 @@
+//! Content heuristics for disambiguating languages that share an extension.
+
+use super::Language;
+use regex::bytes::Regex;
+use std::sync::OnceLock;
+
+/// The maximum number of bytes from the start of a file inspected by the
+/// heuristics.
+const HEURISTICS_MAX_BYTES: usize = 64 * 1024;
+
+/// A rule that selects a language when its pattern matches the content.
+struct Rule {
+    /// The name of the language selected by the rule.
+    language: &'static str,
+    /// The pattern that must match the content. An empty pattern always
+    /// matches, and can be used as a fallback.
+    pattern: &'static str,
+}
+
+/// A set of rules applying to files with any of the given extensions. Rules
+/// are evaluated in order, and the first match wins.
+struct Heuristic {
+    /// The extensions the heuristic applies to.
+    extensions: &'static [&'static str],
+    /// The rules to evaluate.
+    rules: &'static [Rule],
+}
+
+/// The pattern identifying Objective-C source.
+const OBJECTIVE_C_PATTERN: &str = r"(?m)^\s*(@(interface|class|protocol|property|end|synchronised|selector|implementation)\b|#import\s+.+\.h[\x22>])";
+
+/// All known heuristics, modeled after those used by GitHub linguist.
+const HEURISTICS: &[Heuristic] = &[
+    Heuristic {
+        extensions: &["cl"],
+        rules: &[
+            Rule {
+                language: "Common Lisp",
+                pattern: r"(?mi)^\s*\((defun|in-package|defpackage) ",
+            },
+            Rule {
+                language: "OpenCL",
+                pattern: r"(?m)/\* |//|^\}",
+            },
+        ],
+    },
+    Heuristic {
+        extensions: &["h"],
+        rules: &[
+            Rule {
+                language: "Objective-C",
+                pattern: OBJECTIVE_C_PATTERN,
+            },
+            Rule {
+                language: "C++",
+                pattern: r"(?m)^\s*#\s*include <(cstdint|string|vector|map|list|array|bitset|queue|stack|forward_list|unordered_map|unordered_set|(i|o|io)stream)>|^\s*template\s*<|^[ \t]*(try|constexpr)\b|^[ \t]*catch\s*\(|^[ \t]*(class|(using[ \t]+)?namespace)\s+\w+|^[ \t]*(private|public|protected):$|std::\w+",
+            },
+        ],
+    },
+    Heuristic {
+        extensions: &["m"],
+        rules: &[
+            Rule {
+                language: "Objective-C",
+                pattern: OBJECTIVE_C_PATTERN,
+            },
+            Rule {
+                language: "Mercury",
+                pattern: r":- module",
+            },
+            Rule {
+                language: "MATLAB",
+                pattern: r"(?m)^\s*%|^\s*function\b.*$|^\s*(end|endfunction)\s*;?\s*$",
+            },
+        ],
+    },
+    Heuristic {
+        extensions: &["pl"],
+        rules: &[
+            Rule {
+                language: "Perl",
+                pattern: r"\buse\s+(strict\b|v?5\b)",
+            },
+            Rule {
+                language: "Prolog",
+                pattern: r"(?m)^[^#]*:-",
+            },
+        ],
+    },
+    Heuristic {
+        extensions: &["pp"],
+        rules: &[
+            Rule {
+                language: "Pascal",
+                pattern: r"(?m)^\s*end[.;]",
+            },
+            Rule {
+                language: "Puppet",
+                pattern: r"(?m)^\s+\w+\s+=>\s",
+            },
+        ],
+    },
+    Heuristic {
+        extensions: &["ts"],
+        rules: &[Rule {
+            language: "XML",
+            pattern: r"\A\s*<\?xml|<TS\b",
+        }],
+    },
+    Heuristic {
+        extensions: &["v"],
+        rules: &[
+            Rule {
+                language: "Coq",
+                pattern: r"(?m)(^|\s)(Proof|Qed)\.($|\s)|(^|\s)Require[ \t]+(Import|Export)\s",
+            },
+            Rule {
+                language: "V",
+                pattern: r"(?m)\$(if|else)[ \t]|^[ \t]*fn\s+[^\s()]+\(.*?\).*?\{|^[ \t]*for\s*\{",
+            },
+            Rule {
+                language: "Verilog",
+                pattern: "",
+            },
+        ],
+    },
+];
+
+/// Gets the compiled patterns for every rule, in the same order as
+/// `HEURISTICS`.
+fn compiled_patterns() -> &'static [Vec<Regex>] {
+    static PATTERNS: OnceLock<Vec<Vec<Regex>>> = OnceLock::new();
+
+    PATTERNS.get_or_init(|| {
+        HEURISTICS
+            .iter()
+            .map(|heuristic| {
+                heuristic
+                    .rules
+                    .iter()
+                    .map(|rule| Regex::new(rule.pattern).unwrap())
+                    .collect()
+            })
+            .collect()
+    })
+}
+
+/// Chooses between the candidate languages for an extension by inspecting the
+/// file's content. The first candidate is used when no rule matches.
+pub fn disambiguate(ext: &str, candidates: &[Language], content: &[u8]) -> Language {
+    let content = &content[..content.len().min(HEURISTICS_MAX_BYTES)];
+
+    HEURISTICS
+        .iter()
+        .zip(compiled_patterns())
+        .filter(|(heuristic, _)| heuristic.extensions.contains(&ext))
+        .flat_map(|(heuristic, patterns)| heuristic.rules.iter().zip(patterns))
+        .filter(|(_, pattern)| pattern.is_match(content))
+        .find_map(|(rule, _)| {
+            candidates
+                .iter()
+                .find(|language| language.name() == rule.language)
+        })
+        .or_else(|| candidates.first())
+        .cloned()
+        .unwrap_or_else(Language::unknown)
+}
+
+/// Heuristics tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::LanguageRegistry;
+
+    /// Disambiguates a file with the given extension and contents, returning
+    /// the name of the chosen language.
+    fn check(ext: &str, content: &str) -> String {
+        let registry = LanguageRegistry::builtin();
+        let candidates = registry.extension_candidates(ext);
+        disambiguate(ext, candidates, content.as_bytes())
+            .name()
+            .to_owned()
+    }
+
+    /// Tests that all patterns compile.
+    #[test]
+    fn test_patterns_compile() {
+        assert_eq!(compiled_patterns().len(), HEURISTICS.len());
+    }
+
+    /// Tests disambiguation of `.h` headers.
+    #[test]
+    fn test_headers() {
+        assert_eq!(check("h", "int add(int a, int b);\n"), "C");
+        assert_eq!(
+            check(
+                "h",
+                "#import <Foundation/Foundation.h>\n@interface Foo : NSObject\n@end\n"
+            ),
+            "Objective-C"
+        );
+        assert_eq!(check("h", "#include <vector>\nnamespace foo {\n}\n"), "C++");
+        assert_eq!(
+            check("h", "template <typename T>\nT max(T a, T b);\n"),
+            "C++"
+        );
+    }
+
+    /// Tests disambiguation of `.m` files.
+    #[test]
+    fn test_m_files() {
+        assert_eq!(
+            check("m", "@implementation Foo\n- (void)bar {}\n@end\n"),
+            "Objective-C"
+        );
+        assert_eq!(
+            check("m", "% compute things\nfunction y = f(x)\n  y = x;\nend\n"),
+            "MATLAB"
+        );
+        assert_eq!(check("m", ":- module hello.\n:- interface.\n"), "Mercury");
+    }
+
+    /// Tests disambiguation of `.pl`, `.pp`, `.cl` and `.v` files.
+    #[test]
+    fn test_other_shared_extensions() {
+        assert_eq!(check("pl", "use strict;\nmy $x = 1;\n"), "Perl");
+        assert_eq!(
+            check("pl", "parent(tom, bob).\nchild(X) :- parent(_, X).\n"),
+            "Prolog"
+        );
+        assert_eq!(check("pp", "program Hello;\nbegin\nend.\n"), "Pascal");
+        assert_eq!(
+            check("pp", "file { '/etc/motd':\n  ensure => present,\n}\n"),
+            "Puppet"
+        );
+        assert_eq!(check("cl", "(defun square (x) (* x x))\n"), "Common Lisp");
+        assert_eq!(check("cl", "__kernel void add() {\n}\n"), "OpenCL");
+        assert_eq!(
+            check("v", "Theorem t : True.\nProof. trivial. Qed.\n"),
+            "Coq"
+        );
+        assert_eq!(check("v", "fn main() {\n\tprintln('hi')\n}\n"), "V");
+        assert_eq!(
+            check("v", "module top (input clk);\nendmodule\n"),
+            "Verilog"
+        );
+    }
+
+    /// Tests disambiguation of `.ts` files.
+    #[test]
+    fn test_ts_files() {
+        assert_eq!(check("ts", "export const x: number = 1;\n"), "TypeScript");
+        assert_eq!(
+            check(
+                "ts",
+                "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<!DOCTYPE TS>\n<TS version=\"2.1\">\n"
+            ),
+            "XML"
+        );
+    }
+}
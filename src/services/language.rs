@@ -1,134 +1,808 @@
 //! Programming language services.
 
+use super::{CommentSyntax, IndentStyle};
+use fnv::FnvHasher;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io;
+use std::path::PathBuf;
+use std::sync::{Arc, OnceLock};
+
 /// The default name for unknown languages.
 const OTHER_LANGUAGE_NAME: &str = "Other";
 
 /// The default color for unknown languages.
 const OTHER_LANGUAGE_COLOR: &str = "#9f9f9f";
 
-/// A programming language.
-#[allow(clippy::missing_docs_in_private_items)]
+/// The built-in language definitions.
+const BUILTIN_DEFINITIONS: &str = include_str!("../../assets/languages.toml");
+
+/// The name of the directory holding the application's configuration files.
+pub const CONFIG_DIR_NAME: &str = "code-stats";
+
+/// The name of the user's language definition file within the configuration
+/// directory.
+const USER_DEFINITIONS_FILE_NAME: &str = "languages.toml";
+
+/// The category of a language.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LanguageCategory {
+    /// A programming language.
+    Programming,
+    /// A markup or styling language.
+    Markup,
+    /// A data or configuration format.
+    Data,
+    /// Prose, such as documentation.
+    Prose,
+}
+
+impl LanguageCategory {
+    /// All language categories.
+    pub const ALL: [Self; 4] = [Self::Programming, Self::Markup, Self::Data, Self::Prose];
+
+    /// Gets the display name of the category.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Programming => "programming",
+            Self::Markup => "markup",
+            Self::Data => "data",
+            Self::Prose => "prose",
+        }
+    }
+
+    /// Gets the index of the category within `LanguageCategory::ALL`.
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// A selection of language categories, used to choose which languages count
+/// towards statistics. By default, only programming and markup languages are
+/// selected, as on GitHub.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
-pub enum Language {
-    Assembly,
-    Batch,
-    C,
-    CPlusPlus,
-    CSharp,
-    Css,
-    Go,
-    Html,
-    Java,
-    JavaScript,
-    Less,
-    Lua,
-    Nim,
-    ObjectiveC,
-    Php,
-    Python,
-    Rust,
-    Sass,
-    Scss,
-    Shell,
-    Sql,
-    TypeScript,
-    Vue,
-    Unknown,
+pub struct CategorySelection([bool; LanguageCategory::ALL.len()]);
+
+impl CategorySelection {
+    /// Gets whether the category is selected.
+    pub fn contains(&self, category: LanguageCategory) -> bool {
+        self.0[category.index()]
+    }
+
+    /// Gets a copy of the selection with the category selected or deselected.
+    pub fn with(mut self, category: LanguageCategory, selected: bool) -> Self {
+        self.0[category.index()] = selected;
+        self
+    }
+
+    /// Gets whether the language counts towards statistics under this
+    /// selection. Unknown languages never do.
+    pub fn includes(&self, language: &Language) -> bool {
+        language
+            .category()
+            .is_some_and(|category| self.contains(category))
+    }
 }
 
-impl Language {
-    /// Creates a new language instance given a file extension.
-    pub fn new(ext: &str) -> Self {
-        match ext {
-            "asm" => Self::Assembly,
-            "bat" | "cmd" => Self::Batch,
-            "c" | "h" => Self::C,
-            "cpp" | "cc" | "hpp" => Self::CPlusPlus,
-            "cs" => Self::CSharp,
-            "css" => Self::Css,
-            "go" => Self::Go,
-            "html" => Self::Html,
-            "java" => Self::Java,
-            "js" | "jsx" => Self::JavaScript,
-            "less" => Self::Less,
-            "lua" => Self::Lua,
-            "nim" => Self::Nim,
-            "m" => Self::ObjectiveC,
-            "php" => Self::Php,
-            "py" | "pyw" => Self::Python,
-            "rs" => Self::Rust,
-            "sass" => Self::Sass,
-            "scss" => Self::Scss,
-            "sh" | "bash" => Self::Shell,
-            "sql" => Self::Sql,
-            "ts" | "tsx" => Self::TypeScript,
-            "vue" => Self::Vue,
-            _ => Self::Unknown,
+impl FromIterator<LanguageCategory> for CategorySelection {
+    fn from_iter<I>(categories: I) -> Self
+    where
+        I: IntoIterator<Item = LanguageCategory>,
+    {
+        categories.into_iter().fold(
+            Self([false; LanguageCategory::ALL.len()]),
+            |selection, category| selection.with(category, true),
+        )
+    }
+}
+
+impl Default for CategorySelection {
+    fn default() -> Self {
+        Self([true, true, false, false])
+    }
+}
+
+/// A language entry as written in a definition file. Every field but the name
+/// is optional, so that an entry for an existing language only overrides the
+/// fields it specifies.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct LanguageEntry {
+    /// The name of the language, which must not be empty.
+    #[serde(deserialize_with = "deserialize_name")]
+    pub name: String,
+    /// The color used to display the language.
+    pub color: Option<String>,
+    /// The category of the language.
+    pub category: Option<LanguageCategory>,
+    /// File extensions, without the leading dot.
+    pub extensions: Option<Vec<String>>,
+    /// Exact file names.
+    pub filenames: Option<Vec<String>>,
+    /// Interpreters named in shebang lines.
+    pub interpreters: Option<Vec<String>>,
+    /// Alternative names, as used in modelines.
+    pub aliases: Option<Vec<String>>,
+    /// Markers that begin a comment running to the end of a line.
+    pub line_comments: Option<Vec<String>>,
+    /// Pairs of markers that begin and end a block comment.
+    pub block_comments: Option<Vec<(String, String)>>,
+    /// Whether block comments can be nested.
+    pub nested_comments: Option<bool>,
+    /// Pairs of delimiters for strings on a single line.
+    pub strings: Option<Vec<(String, String)>>,
+    /// Pairs of delimiters for strings spanning multiple lines.
+    pub multi_line_strings: Option<Vec<(String, String)>>,
+    /// Pairs of delimiters for raw strings without escape sequences.
+    pub raw_strings: Option<Vec<(String, String)>>,
+    /// Whether single quotes begin character literals.
+    pub char_literals: Option<bool>,
+    /// Whether lines are expected to be indented with tabs rather than
+    /// spaces.
+    pub tab_indentation: Option<bool>,
+}
+
+/// Deserializes the name of a language entry, rejecting empty names.
+fn deserialize_name<'de, D>(deserializer: D) -> Result<String, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let name = String::deserialize(deserializer)?;
+
+    if name.trim().is_empty() {
+        return Err(de::Error::custom("language names must not be empty"));
+    }
+
+    Ok(name)
+}
+
+/// The contents of a language definition file.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LanguageDefinitions {
+    /// The language entries.
+    #[serde(rename = "language")]
+    pub languages: Vec<LanguageEntry>,
+}
+
+impl LanguageDefinitions {
+    /// Parses language definitions from a string.
+    pub fn parse(source: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(source)
+    }
+}
+
+/// The complete definition of a language.
+#[derive(Debug, Clone, PartialEq, Hash)]
+struct LanguageDefinition {
+    /// The name of the language.
+    name: String,
+    /// The color used to display the language.
+    color: String,
+    /// The category of the language. This is `None` only for the unknown
+    /// language.
+    category: Option<LanguageCategory>,
+    /// File extensions, without the leading dot.
+    extensions: Vec<String>,
+    /// Exact file names.
+    filenames: Vec<String>,
+    /// Interpreters named in shebang lines.
+    interpreters: Vec<String>,
+    /// Alternative names, as used in modelines.
+    aliases: Vec<String>,
+    /// The comment syntax of the language.
+    comment_syntax: CommentSyntax,
+    /// Whether lines are expected to be indented with tabs rather than
+    /// spaces.
+    tab_indentation: bool,
+}
+
+impl LanguageDefinition {
+    /// Creates a new definition with the given name and default values.
+    fn new(name: &str) -> Self {
+        Self {
+            name: name.to_owned(),
+            color: OTHER_LANGUAGE_COLOR.to_owned(),
+            category: Some(LanguageCategory::Programming),
+            extensions: vec![],
+            filenames: vec![],
+            interpreters: vec![],
+            aliases: vec![],
+            comment_syntax: CommentSyntax::default(),
+            tab_indentation: false,
         }
     }
 
+    /// Overrides the fields of the definition that are set in the entry.
+    fn apply(&mut self, entry: LanguageEntry) {
+        let syntax = &mut self.comment_syntax;
+
+        macro_rules! apply_fields {
+            ( $( $target:expr => $field:ident ),* $(,)? ) => {
+                $(
+                    if let Some(value) = entry.$field {
+                        $target = value;
+                    }
+                )*
+            };
+        }
+
+        apply_fields!(
+            self.color => color,
+            self.extensions => extensions,
+            self.filenames => filenames,
+            self.interpreters => interpreters,
+            self.aliases => aliases,
+            self.tab_indentation => tab_indentation,
+            syntax.line_comments => line_comments,
+            syntax.block_comments => block_comments,
+            syntax.nested_block_comments => nested_comments,
+            syntax.strings => strings,
+            syntax.multi_line_strings => multi_line_strings,
+            syntax.raw_strings => raw_strings,
+            syntax.char_literals => char_literals,
+        );
+
+        if let Some(category) = entry.category {
+            self.category = Some(category);
+        }
+    }
+}
+
+/// A programming language. Languages are cheap to clone, and are compared by
+/// name.
+#[derive(Debug, Clone)]
+pub struct Language(Arc<LanguageDefinition>);
+
+impl Language {
+    /// Gets the language used for files that could not be identified.
+    pub fn unknown() -> Self {
+        static UNKNOWN: OnceLock<Language> = OnceLock::new();
+
+        UNKNOWN
+            .get_or_init(|| {
+                Language(Arc::new(LanguageDefinition {
+                    category: None,
+                    ..LanguageDefinition::new(OTHER_LANGUAGE_NAME)
+                }))
+            })
+            .clone()
+    }
+
     /// Gets whether the language is known.
     pub fn is_known(&self) -> bool {
-        !matches!(self, Self::Unknown)
+        self.0.category.is_some()
     }
 
     /// Gets the name of the language.
-    pub fn name(&self) -> &'static str {
-        match self {
-            Self::Assembly => "Assembly",
-            Self::Batch => "Batch",
-            Self::C => "C",
-            Self::CPlusPlus => "C++",
-            Self::CSharp => "C#",
-            Self::Css => "CSS",
-            Self::Go => "Go",
-            Self::Html => "HTML",
-            Self::Java => "Java",
-            Self::JavaScript => "JavaScript",
-            Self::Less => "Less",
-            Self::Lua => "Lua",
-            Self::Nim => "Nim",
-            Self::ObjectiveC => "Objective-C",
-            Self::Php => "PHP",
-            Self::Python => "Python",
-            Self::Rust => "Rust",
-            Self::Sass => "Sass",
-            Self::Scss => "SCSS",
-            Self::Shell => "Shell",
-            Self::Sql => "SQL",
-            Self::TypeScript => "TypeScript",
-            Self::Vue => "Vue",
-            Self::Unknown => OTHER_LANGUAGE_NAME,
-        }
+    pub fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    /// Gets the category of the language, or `None` if the language is not
+    /// known.
+    pub fn category(&self) -> Option<LanguageCategory> {
+        self.0.category
+    }
+
+    /// Gets the comment syntax of the language.
+    pub fn comment_syntax(&self) -> &CommentSyntax {
+        &self.0.comment_syntax
     }
 
     /// Gets the color of the language.
-    pub fn color(&self) -> &'static str {
-        match self {
-            Self::Assembly => "#6e4c13",
-            Self::Batch => "#c1f12e",
-            Self::C => "#555555",
-            Self::CPlusPlus => "#f34b7d",
-            Self::CSharp => "#178600",
-            Self::Css => "#563d7c",
-            Self::Go => "#00add8",
-            Self::Html => "#e34c26",
-            Self::Java => "#b07219",
-            Self::JavaScript => "#f1e05a",
-            Self::Less => "#1d365d",
-            Self::Lua => "#000080",
-            Self::Nim => "#ffc200",
-            Self::ObjectiveC => "#438eff",
-            Self::Php => "#4f5d95",
-            Self::Python => "#3572a5",
-            Self::Rust => "#dea584",
-            Self::Sass => "#a53b70",
-            Self::Scss => "#c6538c",
-            Self::Shell => "#89e051",
-            Self::Sql => "#e38c00",
-            Self::TypeScript => "#2b7489",
-            Self::Vue => "#41b883",
-            Self::Unknown => OTHER_LANGUAGE_COLOR,
+    pub fn color(&self) -> &str {
+        &self.0.color
+    }
+
+    /// Gets the indentation expected in files written in the language.
+    pub fn indentation(&self) -> IndentStyle {
+        if self.0.tab_indentation {
+            IndentStyle::Tabs
+        } else {
+            IndentStyle::Spaces
+        }
+    }
+}
+
+impl PartialEq for Language {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.name == other.0.name
+    }
+}
+
+impl Eq for Language {}
+
+impl Hash for Language {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.name.hash(state);
+    }
+}
+
+/// A registry of all known languages, indexed for detection.
+#[derive(Debug, Clone)]
+pub struct LanguageRegistry {
+    /// All languages, in definition order.
+    languages: Vec<Language>,
+    /// Languages by lowercase name and alias.
+    by_alias: HashMap<String, Language>,
+    /// Candidate languages by lowercase extension.
+    by_extension: HashMap<String, Vec<Language>>,
+    /// Languages by exact file name.
+    by_filename: HashMap<String, Language>,
+    /// Languages by interpreter.
+    by_interpreter: HashMap<String, Language>,
+    /// An error encountered while loading the user's language definitions.
+    user_definitions_error: Option<String>,
+}
+
+impl LanguageRegistry {
+    /// Creates a registry from a list of definition sets. Later sets add new
+    /// languages, or override fields of languages with the same name.
+    pub fn new<I>(definition_sets: I) -> Self
+    where
+        I: IntoIterator<Item = LanguageDefinitions>,
+    {
+        // Alongside each definition, the index of the last definition set
+        // that specified its extensions and its filenames. Later sets take
+        // precedence, so that a user definition can remap an extension or a
+        // filename claimed by a built-in language.
+        let mut definitions = Vec::<(LanguageDefinition, usize, usize)>::new();
+
+        for (set, entry) in
+            definition_sets
+                .into_iter()
+                .enumerate()
+                .flat_map(|(set, definitions)| {
+                    definitions
+                        .languages
+                        .into_iter()
+                        .map(move |entry| (set, entry))
+                })
+        {
+            let index = match definitions
+                .iter()
+                .position(|(definition, _, _)| definition.name.eq_ignore_ascii_case(&entry.name))
+            {
+                Some(index) => index,
+                None => {
+                    definitions.push((LanguageDefinition::new(&entry.name), set, set));
+                    definitions.len() - 1
+                }
+            };
+
+            let (definition, extensions_set, filenames_set) = &mut definitions[index];
+
+            if entry.extensions.is_some() {
+                *extensions_set = set;
+            }
+
+            if entry.filenames.is_some() {
+                *filenames_set = set;
+            }
+
+            definition.apply(entry);
+        }
+
+        let languages = definitions
+            .into_iter()
+            .filter(|(definition, _, _)| definition.name != OTHER_LANGUAGE_NAME)
+            .map(|(definition, extensions_set, filenames_set)| {
+                (
+                    Language(Arc::new(definition)),
+                    extensions_set,
+                    filenames_set,
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let mut registry = Self {
+            languages: vec![],
+            by_alias: HashMap::new(),
+            by_extension: HashMap::new(),
+            by_filename: HashMap::new(),
+            by_interpreter: HashMap::new(),
+            user_definitions_error: None,
+        };
+        let mut extension_sets = HashMap::<String, usize>::new();
+        let mut filename_sets = HashMap::<String, usize>::new();
+
+        for (language, extensions_set, filenames_set) in &languages {
+            let definition = &language.0;
+
+            for alias in [&definition.name].into_iter().chain(&definition.aliases) {
+                registry
+                    .by_alias
+                    .entry(alias.to_lowercase())
+                    .or_insert_with(|| language.clone());
+            }
+
+            // Candidates from a later definition set replace those from
+            // earlier sets instead of being added after them.
+            for ext in &definition.extensions {
+                let ext = ext.to_lowercase();
+                let claimed = extension_sets.entry(ext.clone()).or_insert(*extensions_set);
+                let candidates = registry.by_extension.entry(ext).or_default();
+
+                if *extensions_set > *claimed {
+                    *claimed = *extensions_set;
+                    candidates.clear();
+                }
+
+                if *extensions_set == *claimed {
+                    candidates.push(language.clone());
+                }
+            }
+
+            for filename in &definition.filenames {
+                let claimed = filename_sets
+                    .entry(filename.clone())
+                    .or_insert(*filenames_set);
+
+                if *filenames_set > *claimed || !registry.by_filename.contains_key(filename) {
+                    *claimed = *filenames_set;
+                    registry
+                        .by_filename
+                        .insert(filename.clone(), language.clone());
+                }
+            }
+
+            for interpreter in &definition.interpreters {
+                registry
+                    .by_interpreter
+                    .entry(interpreter.clone())
+                    .or_insert_with(|| language.clone());
+            }
+        }
+
+        let languages = languages
+            .into_iter()
+            .map(|(language, _, _)| language)
+            .collect();
+        registry.languages = languages;
+        registry
+    }
+
+    /// Creates a registry containing only the built-in languages.
+    #[cfg(test)]
+    pub fn builtin() -> Self {
+        Self::new([builtin_definitions()])
+    }
+
+    /// Gets the shared registry of built-in languages, extended by the user's
+    /// language definition file if one exists.
+    pub fn global() -> Arc<Self> {
+        static GLOBAL: OnceLock<Arc<LanguageRegistry>> = OnceLock::new();
+
+        GLOBAL
+            .get_or_init(|| Arc::new(Self::with_user_definitions(None)))
+            .clone()
+    }
+
+    /// Creates a registry of the built-in languages, extended by the user's
+    /// language definition file and then by a project's own definitions.
+    pub fn with_project_definitions(definitions: LanguageDefinitions) -> Self {
+        Self::with_user_definitions(Some(definitions))
+    }
+
+    /// Creates a registry of the built-in languages, extended by the user's
+    /// language definition file and then by any additional definitions.
+    fn with_user_definitions(additional: Option<LanguageDefinitions>) -> Self {
+        let (user_definitions, error) = match load_user_definitions() {
+            Ok(definitions) => (definitions, None),
+            Err(err) => (None, Some(err)),
+        };
+
+        let mut registry = Self::new(
+            [builtin_definitions()]
+                .into_iter()
+                .chain(user_definitions)
+                .chain(additional),
+        );
+        registry.user_definitions_error = error;
+        registry
+    }
+
+    /// Gets an error encountered while loading the user's language definition
+    /// file, if any.
+    pub fn user_definitions_error(&self) -> Option<&str> {
+        self.user_definitions_error.as_deref()
+    }
+
+    /// Gets a language by name or alias. Matching is case-insensitive.
+    pub fn find_by_alias(&self, alias: &str) -> Option<Language> {
+        self.by_alias.get(&alias.to_lowercase()).cloned()
+    }
+
+    /// Gets a language by its exact name. The unknown language is found by its
+    /// name too.
+    pub fn find_by_name(&self, name: &str) -> Option<Language> {
+        if name == OTHER_LANGUAGE_NAME {
+            return Some(Language::unknown());
+        }
+
+        self.find_by_alias(name)
+            .filter(|language| language.name() == name)
+            .or_else(|| {
+                self.languages
+                    .iter()
+                    .find(|language| language.name() == name)
+                    .cloned()
+            })
+    }
+
+    /// Gets a hash of every language definition in the registry, which changes
+    /// whenever a definition is added or modified.
+    pub fn fingerprint(&self) -> u64 {
+        let mut hasher = FnvHasher::default();
+        self.languages
+            .iter()
+            .for_each(|language| language.0.hash(&mut hasher));
+        hasher.finish()
+    }
+
+    /// Gets the candidate languages associated with a file extension. When
+    /// there is more than one candidate, the first is the most likely.
+    pub fn extension_candidates(&self, ext: &str) -> &[Language] {
+        self.by_extension
+            .get(&ext.to_lowercase())
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Gets the language associated with an exact file name.
+    pub fn find_by_filename(&self, filename: &str) -> Option<Language> {
+        self.by_filename.get(filename).cloned()
+    }
+
+    /// Gets the language associated with a script interpreter, as named in a
+    /// shebang line. Version suffixes such as `python3.11` are ignored.
+    pub fn find_by_interpreter(&self, interpreter: &str) -> Option<Language> {
+        self.by_interpreter
+            .get(interpreter)
+            .or_else(|| {
+                self.by_interpreter
+                    .get(interpreter.trim_end_matches(|c: char| c.is_ascii_digit() || c == '.'))
+            })
+            .cloned()
+    }
+}
+
+/// Parses the built-in language definitions.
+fn builtin_definitions() -> LanguageDefinitions {
+    LanguageDefinitions::parse(BUILTIN_DEFINITIONS).expect("invalid built-in language definitions")
+}
+
+/// Gets the path to the user's language definition file.
+pub fn user_definitions_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR_NAME).join(USER_DEFINITIONS_FILE_NAME))
+}
+
+/// Loads the user's language definition file. A missing file is not an
+/// error.
+fn load_user_definitions() -> Result<Option<LanguageDefinitions>, String> {
+    let Some(path) = user_definitions_path() else {
+        return Ok(None);
+    };
+
+    match fs::read_to_string(&path) {
+        Ok(source) => LanguageDefinitions::parse(&source)
+            .map(Some)
+            .map_err(|err| format!("{}: {}", path.display(), err)),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(err) => Err(format!("{}: {}", path.display(), err)),
+    }
+}
+
+/// Language registry tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::detect_language;
+    use std::path::Path;
+
+    /// Tests that the built-in definitions are valid and indexed.
+    #[test]
+    fn test_builtin_definitions() {
+        let registry = LanguageRegistry::builtin();
+        let rust = registry.find_by_alias("rust").unwrap();
+        assert_eq!(rust.name(), "Rust");
+        assert_eq!(rust.category(), Some(LanguageCategory::Programming));
+        assert!(rust.comment_syntax().nested_block_comments);
+        assert!(rust.comment_syntax().char_literals);
+        assert_eq!(rust.indentation(), IndentStyle::Spaces);
+        assert_eq!(
+            registry.find_by_alias("go").unwrap().indentation(),
+            IndentStyle::Tabs
+        );
+        assert!(!rust.comment_syntax().raw_strings.is_empty());
+        assert_eq!(registry.extension_candidates("RS"), &[rust]);
+        assert!(registry.extension_candidates("h").len() > 1);
+        assert_eq!(
+            registry.find_by_interpreter("python3.11").unwrap().name(),
+            "Python"
+        );
+    }
+
+    /// Tests that the built-in catalog covers common languages beyond the
+    /// most popular ones.
+    #[test]
+    fn test_builtin_catalog() {
+        let registry = LanguageRegistry::builtin();
+        assert!(registry.languages.len() > 300);
+
+        for (ext, name) in [
+            ("kt", "Kotlin"),
+            ("swift", "Swift"),
+            ("dart", "Dart"),
+            ("hs", "Haskell"),
+            ("ex", "Elixir"),
+            ("scala", "Scala"),
+            ("zig", "Zig"),
+            ("toml", "TOML"),
+            ("yml", "YAML"),
+            ("json", "JSON"),
+            ("md", "Markdown"),
+            ("tf", "HCL"),
+            ("proto", "Protocol Buffer"),
+            ("graphql", "GraphQL"),
+        ] {
+            assert_eq!(registry.extension_candidates(ext)[0].name(), name);
+        }
+
+        for (filename, name) in [
+            ("Dockerfile", "Dockerfile"),
+            ("Cargo.lock", "TOML"),
+            ("go.mod", "Go Module"),
+            ("LICENSE", "Text"),
+        ] {
+            assert_eq!(registry.find_by_filename(filename).unwrap().name(), name);
         }
+
+        for (alias, name) in [
+            ("protocol buffers", "Protocol Buffer"),
+            ("ipython notebook", "Jupyter Notebook"),
+            ("gnu asm", "Unix Assembly"),
+            ("nginx configuration file", "Nginx"),
+        ] {
+            assert_eq!(registry.find_by_alias(alias).unwrap().name(), name);
+        }
+
+        for alias in ["notebook", "buffers", "protocol", "basic", "visual"] {
+            assert!(registry.find_by_alias(alias).is_none(), "{alias}");
+        }
+    }
+
+    /// Tests category selections.
+    #[test]
+    fn test_category_selection() {
+        let registry = LanguageRegistry::builtin();
+        let rust = registry.find_by_alias("rust").unwrap();
+        let json = registry.find_by_alias("json").unwrap();
+        let selection = CategorySelection::default();
+
+        assert!(selection.includes(&rust));
+        assert!(!selection.includes(&json));
+        assert!(!selection.includes(&Language::unknown()));
+
+        let selection = selection
+            .with(LanguageCategory::Programming, false)
+            .with(LanguageCategory::Data, true);
+        assert!(!selection.includes(&rust));
+        assert!(selection.includes(&json));
+        assert!(!selection.includes(&Language::unknown()));
+
+        let collected = [LanguageCategory::Markup, LanguageCategory::Data]
+            .into_iter()
+            .collect();
+        assert_eq!(selection, collected);
+    }
+
+    /// Tests that later definitions add and override languages.
+    #[test]
+    fn test_overrides() {
+        let user = LanguageDefinitions::parse(
+            r##"
+            [[language]]
+            name = "rust"
+            color = "#000000"
+
+            [[language]]
+            name = "Acme Config"
+            color = "#4a90e2"
+            extensions = ["acme"]
+            line_comments = ["//"]
+            "##,
+        )
+        .unwrap();
+        let registry = LanguageRegistry::new([builtin_definitions(), user]);
+
+        let rust = registry.find_by_alias("Rust").unwrap();
+        assert_eq!(rust.color(), "#000000");
+        assert_eq!(rust.name(), "Rust");
+        assert_eq!(registry.extension_candidates("rs"), &[rust]);
+
+        let acme = &registry.extension_candidates("acme")[0];
+        assert_eq!(acme.name(), "Acme Config");
+        assert_eq!(acme.category(), Some(LanguageCategory::Programming));
+        assert!(acme.is_known());
+        assert!(!Language::unknown().is_known());
+    }
+
+    /// Tests that extensions and filenames in later definitions take
+    /// precedence over those of built-in languages.
+    #[test]
+    fn test_extension_overrides() {
+        let user = LanguageDefinitions::parse(
+            r##"
+            [[language]]
+            name = "C++"
+            extensions = ["cpp", "h"]
+
+            [[language]]
+            name = "Acme Make"
+            filenames = ["Makefile"]
+            "##,
+        )
+        .unwrap();
+        let builtin = LanguageRegistry::builtin();
+        let registry = LanguageRegistry::new([builtin_definitions(), user]);
+
+        assert!(builtin.extension_candidates("h").len() > 1);
+        let candidates = registry.extension_candidates("h");
+        assert_eq!(candidates.len(), 1);
+        assert_eq!(candidates[0].name(), "C++");
+
+        let objective_c = b"#import <Foundation/Foundation.h>\n@interface Foo : NSObject\n@end\n";
+        let language = detect_language(&registry, Path::new("foo.h"), objective_c);
+        assert_eq!(language.name(), "C++");
+
+        let c = registry.extension_candidates("c");
+        assert_eq!(c[0].name(), "C");
+
+        assert_eq!(
+            builtin.find_by_filename("Makefile").unwrap().name(),
+            "Makefile"
+        );
+        assert_eq!(
+            registry.find_by_filename("Makefile").unwrap().name(),
+            "Acme Make"
+        );
+    }
+
+    /// Tests that unknown fields are rejected.
+    #[test]
+    fn test_invalid_definitions() {
+        assert!(
+            LanguageDefinitions::parse("[[language]]\nname = \"X\"\ncolour = \"red\"\n").is_err()
+        );
+        assert!(LanguageDefinitions::parse("[[language]]\ncolor = \"red\"\n").is_err());
+        assert!(LanguageDefinitions::parse("[[language]]\nname = \" \"\n").is_err());
+        assert!(LanguageDefinitions::parse("[[language]]\nname = \"X\"\n").is_ok());
+    }
+
+    /// Tests that the fingerprint changes with any field of a definition.
+    #[test]
+    fn test_fingerprint() {
+        let registry = |source| {
+            LanguageRegistry::new([
+                builtin_definitions(),
+                LanguageDefinitions::parse(source).unwrap(),
+            ])
+        };
+        let fingerprint = LanguageRegistry::builtin().fingerprint();
+        assert_eq!(registry("").fingerprint(), fingerprint);
+        assert_ne!(
+            registry("[[language]]\nname = \"Rust\"\nchar_literals = false\n").fingerprint(),
+            fingerprint
+        );
+        assert_ne!(
+            registry("[[language]]\nname = \"Rust\"\ntab_indentation = true\n").fingerprint(),
+            fingerprint
+        );
     }
 }
@@ -0,0 +1,604 @@
+//! Line classification services.
+
+use super::FileCounts;
+use memchr::{memchr, memchr2};
+use serde::{Deserialize, Serialize};
+use std::mem;
+
+/// The most bytes of a line spanning several chunks that are kept for
+/// classification. The rest of such a line is skipped, so that memory use
+/// stays bounded on files with extremely long lines, such as minified files.
+const MAX_CARRIED_LINE_BYTES: usize = 64 * 1024;
+
+/// Comment and string syntax for a language. The default syntax has no
+/// comments or strings at all, so every non-blank line is considered code.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct CommentSyntax {
+    /// Markers that begin a comment running to the end of the line.
+    pub line_comments: Vec<String>,
+    /// Pairs of markers that begin and end a block comment.
+    pub block_comments: Vec<(String, String)>,
+    /// Whether block comments can be nested within one another.
+    pub nested_block_comments: bool,
+    /// Pairs of string delimiters. Strings that are not terminated by the end
+    /// of the line are considered closed.
+    pub strings: Vec<(String, String)>,
+    /// Pairs of string delimiters for strings that may span multiple lines.
+    pub multi_line_strings: Vec<(String, String)>,
+    /// Pairs of delimiters for raw strings, which may span multiple lines and
+    /// have no escape sequences. They are matched before other strings, so
+    /// longer opening delimiters should come first.
+    pub raw_strings: Vec<(String, String)>,
+    /// Whether a single quote begins a character literal holding a single
+    /// character or escape sequence. Other single quotes, such as those of
+    /// Rust lifetimes, are code.
+    pub char_literals: bool,
+}
+
+/// The kind of a single line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineKind {
+    /// A line containing code, possibly alongside a comment.
+    Code,
+    /// A line containing only comments.
+    Comment,
+    /// A line containing only whitespace.
+    Blank,
+}
+
+/// The state carried between lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ClassifierState {
+    /// Not inside a comment or string.
+    #[default]
+    Code,
+    /// Inside a block comment. The values are the index of the block comment
+    /// pair and the current nesting depth.
+    BlockComment(usize, usize),
+    /// Inside a multi-line string. The value is the index of the string pair.
+    MultiLineString(usize),
+    /// Inside a raw string. The value is the index of the string pair.
+    RawString(usize),
+}
+
+/// A stateful line classifier. Lines must be fed in order, since block
+/// comments and multi-line strings carry state from one line to the next.
+#[derive(Debug, Clone)]
+pub struct LineClassifier<'a> {
+    /// The syntax used to classify lines.
+    syntax: &'a CommentSyntax,
+    /// The state at the start of the next line.
+    state: ClassifierState,
+}
+
+impl<'a> LineClassifier<'a> {
+    /// Creates a new line classifier for the given syntax.
+    pub fn new(syntax: &'a CommentSyntax) -> Self {
+        Self {
+            syntax,
+            state: ClassifierState::default(),
+        }
+    }
+
+    /// Classifies a single line, without its line terminator.
+    pub fn classify(&mut self, line: &[u8]) -> LineKind {
+        let starts_in_string = matches!(
+            self.state,
+            ClassifierState::MultiLineString(_) | ClassifierState::RawString(_)
+        );
+
+        if line.iter().all(u8::is_ascii_whitespace) {
+            return if starts_in_string {
+                LineKind::Code
+            } else {
+                LineKind::Blank
+            };
+        }
+
+        let mut has_code = starts_in_string;
+        let mut has_comment = false;
+        let mut line_string = None::<&[u8]>;
+        let mut idx = 0;
+
+        while idx < line.len() {
+            let rest = &line[idx..];
+
+            if let Some(close) = line_string {
+                if rest[0] == b'\\' {
+                    idx += 2;
+                } else if rest.starts_with(close) {
+                    line_string = None;
+                    idx += close.len();
+                } else {
+                    idx += 1;
+                }
+
+                continue;
+            }
+
+            match self.state {
+                ClassifierState::BlockComment(pair, depth) => {
+                    has_comment = true;
+                    let (open, close) = &self.syntax.block_comments[pair];
+
+                    if self.syntax.nested_block_comments && rest.starts_with(open.as_bytes()) {
+                        self.state = ClassifierState::BlockComment(pair, depth + 1);
+                        idx += open.len();
+                    } else if rest.starts_with(close.as_bytes()) {
+                        self.state = if depth > 1 {
+                            ClassifierState::BlockComment(pair, depth - 1)
+                        } else {
+                            ClassifierState::Code
+                        };
+                        idx += close.len();
+                    } else {
+                        idx += 1;
+                    }
+                }
+                ClassifierState::MultiLineString(pair) => {
+                    let (_, close) = &self.syntax.multi_line_strings[pair];
+
+                    if rest[0] == b'\\' {
+                        idx += 2;
+                    } else if rest.starts_with(close.as_bytes()) {
+                        self.state = ClassifierState::Code;
+                        idx += close.len();
+                    } else {
+                        idx += 1;
+                    }
+                }
+                ClassifierState::RawString(pair) => {
+                    let (_, close) = &self.syntax.raw_strings[pair];
+
+                    if rest.starts_with(close.as_bytes()) {
+                        self.state = ClassifierState::Code;
+                        idx += close.len();
+                    } else {
+                        idx += 1;
+                    }
+                }
+                ClassifierState::Code => {
+                    if rest[0].is_ascii_whitespace() {
+                        idx += 1;
+                    } else if let Some((pair, (open, _))) =
+                        find_pair(&self.syntax.block_comments, rest)
+                    {
+                        has_comment = true;
+                        self.state = ClassifierState::BlockComment(pair, 1);
+                        idx += open.len();
+                    } else if starts_with_any(&self.syntax.line_comments, rest) {
+                        has_comment = true;
+                        break;
+                    } else if let Some((pair, (open, _))) =
+                        find_pair(&self.syntax.raw_strings, rest)
+                    {
+                        has_code = true;
+                        self.state = ClassifierState::RawString(pair);
+                        idx += open.len();
+                    } else if let Some(len) = self
+                        .syntax
+                        .char_literals
+                        .then(|| char_literal_len(rest))
+                        .flatten()
+                    {
+                        has_code = true;
+                        idx += len;
+                    } else if let Some((pair, (open, _))) =
+                        find_pair(&self.syntax.multi_line_strings, rest)
+                    {
+                        has_code = true;
+                        self.state = ClassifierState::MultiLineString(pair);
+                        idx += open.len();
+                    } else if let Some((_, (open, close))) = find_pair(&self.syntax.strings, rest) {
+                        has_code = true;
+                        line_string = Some(close.as_bytes());
+                        idx += open.len();
+                    } else {
+                        has_code = true;
+                        idx += 1;
+                    }
+                }
+            }
+        }
+
+        if has_code {
+            LineKind::Code
+        } else if has_comment {
+            LineKind::Comment
+        } else {
+            LineKind::Blank
+        }
+    }
+}
+
+/// How the lines of a file are counted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum LineCountMode {
+    /// Lines end at a line feed, a carriage return followed by a line feed, or
+    /// a lone carriage return. A last line without a line ending is counted
+    /// unless it is empty, so an empty file has no lines and a file ending in
+    /// a line ending has no extra empty line.
+    #[default]
+    Lines,
+    /// Only line feeds end lines, and a last line without one is not counted,
+    /// so that the number of lines is the number of line feeds in the decoded
+    /// text, as `wc -l` reports for UTF-8 text. Carriage returns before line
+    /// feeds are ignored, and lone carriage returns do not end lines.
+    Newlines,
+}
+
+/// Counts and classifies the lines of a file fed to it in chunks of any size.
+/// Line endings are found with a vectorized search, and only a line spanning
+/// two chunks is copied.
+#[derive(Debug, Clone)]
+pub struct LineCounter<'a> {
+    /// The classifier for the lines of the file.
+    classifier: LineClassifier<'a>,
+    /// How lines are counted.
+    mode: LineCountMode,
+    /// The start of a line continued from an earlier chunk.
+    carried: Vec<u8>,
+    /// Whether the previous chunk ended in a carriage return, so that a line
+    /// feed starting the next chunk belongs to the same line ending.
+    after_carriage_return: bool,
+    /// The counts so far.
+    counts: FileCounts,
+}
+
+impl<'a> LineCounter<'a> {
+    /// Creates a new line counter for the given syntax.
+    pub fn new(syntax: &'a CommentSyntax, mode: LineCountMode) -> Self {
+        Self {
+            classifier: LineClassifier::new(syntax),
+            mode,
+            carried: Vec::new(),
+            after_carriage_return: false,
+            counts: FileCounts::default(),
+        }
+    }
+
+    /// Counts the lines ending in the next chunk of the file, carrying over
+    /// the line it ends in the middle of.
+    pub fn feed(&mut self, mut chunk: &[u8]) {
+        if chunk.is_empty() {
+            return;
+        }
+
+        if mem::take(&mut self.after_carriage_return) {
+            chunk = chunk.strip_prefix(b"\n").unwrap_or(chunk);
+        }
+
+        let mut start = 0;
+
+        while let Some(offset) = self.find_line_ending(&chunk[start..]) {
+            let end = start + offset;
+            let line = &chunk[start..end];
+
+            if self.carried.is_empty() {
+                self.count_line(line);
+            } else {
+                self.carry(line);
+                let carried = mem::take(&mut self.carried);
+                self.count_line(&carried);
+                self.carried = carried;
+                self.carried.clear();
+            }
+
+            start = end + 1;
+
+            if chunk[end] == b'\r' {
+                match chunk.get(start) {
+                    Some(b'\n') => start += 1,
+                    Some(_) => {}
+                    None => self.after_carriage_return = true,
+                }
+            }
+        }
+
+        self.carry(&chunk[start..]);
+    }
+
+    /// Counts the last line of the file and returns the counts. The bytes are
+    /// left for the caller to count.
+    pub fn finish(mut self) -> FileCounts {
+        if self.mode == LineCountMode::Lines && !self.carried.is_empty() {
+            let carried = mem::take(&mut self.carried);
+            self.count_line(&carried);
+        }
+
+        self.counts
+    }
+
+    /// Finds the end of the next line in a chunk.
+    fn find_line_ending(&self, text: &[u8]) -> Option<usize> {
+        match self.mode {
+            LineCountMode::Lines => memchr2(b'\n', b'\r', text),
+            LineCountMode::Newlines => memchr(b'\n', text),
+        }
+    }
+
+    /// Keeps part of a line until the rest of it is fed.
+    fn carry(&mut self, part: &[u8]) {
+        let room = MAX_CARRIED_LINE_BYTES.saturating_sub(self.carried.len());
+        self.carried
+            .extend_from_slice(&part[..part.len().min(room)]);
+    }
+
+    /// Classifies and counts a single line, without its line ending.
+    fn count_line(&mut self, line: &[u8]) {
+        let line = line.strip_suffix(b"\r").unwrap_or(line);
+        self.counts.lines += 1;
+
+        match self.classifier.classify(line) {
+            LineKind::Code => self.counts.code += 1,
+            LineKind::Comment => self.counts.comments += 1,
+            LineKind::Blank => self.counts.blanks += 1,
+        }
+    }
+}
+
+/// Checks whether the text starts with any of the given markers.
+fn starts_with_any(markers: &[String], text: &[u8]) -> bool {
+    markers
+        .iter()
+        .any(|marker| text.starts_with(marker.as_bytes()))
+}
+
+/// Gets the length of the character literal the text starts with, such as
+/// `'a'`, `'"'` or `'\u{1f980}'`, or `None` if it does not start with one.
+fn char_literal_len(text: &[u8]) -> Option<usize> {
+    if text.first() != Some(&b'\'') {
+        return None;
+    }
+
+    match text.get(1)? {
+        b'\\' => {
+            // The escaped character may itself be a quote, as in `'\''`.
+            let close = memchr(b'\'', text.get(3..)?)?;
+            Some(close + 4)
+        }
+        &lead => {
+            let char_len = match lead {
+                0x00..=0x7f => 1,
+                0xc0..=0xdf => 2,
+                0xe0..=0xef => 3,
+                _ => 4,
+            };
+            (text.get(1 + char_len) == Some(&b'\'')).then_some(char_len + 2)
+        }
+    }
+}
+
+/// Finds the first pair of delimiters whose opening delimiter the text starts
+/// with.
+fn find_pair<'a>(
+    pairs: &'a [(String, String)],
+    text: &[u8],
+) -> Option<(usize, &'a (String, String))> {
+    pairs
+        .iter()
+        .enumerate()
+        .find(|(_, (open, _))| text.starts_with(open.as_bytes()))
+}
+
+/// Line classification tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Converts a list of delimiter pairs into owned strings.
+    fn pairs(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs
+            .iter()
+            .map(|(open, close)| (open.to_string(), close.to_string()))
+            .collect()
+    }
+
+    /// C-like syntax used for testing.
+    fn c_like() -> CommentSyntax {
+        CommentSyntax {
+            line_comments: vec!["//".to_owned()],
+            block_comments: pairs(&[("/*", "*/")]),
+            nested_block_comments: false,
+            strings: pairs(&[("\"", "\""), ("'", "'")]),
+            multi_line_strings: vec![],
+            raw_strings: vec![],
+            char_literals: false,
+        }
+    }
+
+    /// Rust-like syntax used for testing.
+    fn rust_like() -> CommentSyntax {
+        CommentSyntax {
+            line_comments: vec!["//".to_owned()],
+            block_comments: pairs(&[("/*", "*/")]),
+            nested_block_comments: true,
+            strings: vec![],
+            multi_line_strings: pairs(&[("\"", "\"")]),
+            raw_strings: pairs(&[("r##\"", "\"##"), ("r#\"", "\"#"), ("r\"", "\"")]),
+            char_literals: true,
+        }
+    }
+
+    /// Classifies every line of the given source.
+    fn classify_all(syntax: &CommentSyntax, source: &str) -> Vec<LineKind> {
+        let mut classifier = LineClassifier::new(syntax);
+        source
+            .split('\n')
+            .map(|line| classifier.classify(line.as_bytes()))
+            .collect()
+    }
+
+    /// Tests line comments, blank lines and trailing comments.
+    #[test]
+    fn test_line_comments() {
+        use LineKind::*;
+
+        let source = "// header\n\nint x = 1; // trailing\n   \n  // indented";
+        assert_eq!(
+            classify_all(&c_like(), source),
+            vec![Comment, Blank, Code, Blank, Comment]
+        );
+    }
+
+    /// Tests block comments spanning several lines.
+    #[test]
+    fn test_block_comments() {
+        use LineKind::*;
+
+        let source = "/*\n * License\n */\nint x; /* a */ int y;\n/* a */ int z;";
+        assert_eq!(
+            classify_all(&c_like(), source),
+            vec![Comment, Comment, Comment, Code, Code]
+        );
+    }
+
+    /// Tests that nested block comments are only closed by the outermost
+    /// terminator.
+    #[test]
+    fn test_nested_block_comments() {
+        use LineKind::*;
+
+        let source = "/* outer\n/* inner */\nstill comment */\nfn main() {}";
+        assert_eq!(
+            classify_all(&rust_like(), source),
+            vec![Comment, Comment, Comment, Code]
+        );
+        assert_eq!(
+            classify_all(&c_like(), source),
+            vec![Comment, Comment, Code, Code]
+        );
+    }
+
+    /// Tests that comment markers inside strings are ignored.
+    #[test]
+    fn test_strings() {
+        use LineKind::*;
+
+        let source = "char *s = \"// not a comment /*\";\nint x;\nchar c = '\"'; // quote";
+        assert_eq!(classify_all(&c_like(), source), vec![Code, Code, Code]);
+
+        let source = "let s = \"\n// inside string\n\n\";\n// comment";
+        assert_eq!(
+            classify_all(&rust_like(), source),
+            vec![Code, Code, Code, Code, Comment]
+        );
+    }
+
+    /// Tests that quotes in character literals and raw strings do not open
+    /// strings, and that lifetimes are not taken for character literals.
+    #[test]
+    fn test_rust_literals() {
+        use LineKind::*;
+
+        let source = "let q = '\"';\n// comment\n\nlet e = '\\'';\n// comment";
+        assert_eq!(
+            classify_all(&rust_like(), source),
+            vec![Code, Comment, Blank, Code, Comment]
+        );
+
+        let source = "fn f<'a>(s: &'a str) -> char { '\\u{1f980}' }\nlet c = '\u{e9}';\n// comment";
+        assert_eq!(
+            classify_all(&rust_like(), source),
+            vec![Code, Code, Comment]
+        );
+
+        let source =
+            "let p = r\"C:\\\";\n// comment\nlet s = r#\"a \"quote\"\n// inside\n\"#;\n// comment";
+        assert_eq!(
+            classify_all(&rust_like(), source),
+            vec![Code, Comment, Code, Code, Code, Comment]
+        );
+    }
+
+    /// Tests that lines without any known syntax are code or blank.
+    #[test]
+    fn test_no_syntax() {
+        use LineKind::*;
+
+        let source = "# not a comment\n\ntext";
+        assert_eq!(
+            classify_all(&CommentSyntax::default(), source),
+            vec![Code, Blank, Code]
+        );
+    }
+
+    /// Counts the lines of the given source, fed in chunks of the given size.
+    fn count_lines(source: &str, mode: LineCountMode, chunk_size: usize) -> FileCounts {
+        let syntax = c_like();
+        let mut counter = LineCounter::new(&syntax, mode);
+        source
+            .as_bytes()
+            .chunks(chunk_size)
+            .for_each(|chunk| counter.feed(chunk));
+        counter.finish()
+    }
+
+    /// Tests that lines are counted the same however the file is split into
+    /// chunks.
+    #[test]
+    fn test_line_counter() {
+        let source = "/* a\r\n * b */\rint x;\n\n// c\nint y;";
+        let lines = count_lines(source, LineCountMode::Lines, source.len());
+        assert_eq!(
+            lines,
+            FileCounts {
+                lines: 6,
+                code: 2,
+                comments: 3,
+                blanks: 1,
+                ..FileCounts::default()
+            }
+        );
+
+        let newlines = count_lines(source, LineCountMode::Newlines, source.len());
+        assert_eq!(
+            newlines,
+            FileCounts {
+                lines: 4,
+                code: 1,
+                comments: 2,
+                blanks: 1,
+                ..FileCounts::default()
+            }
+        );
+
+        for chunk_size in 1..source.len() {
+            assert_eq!(count_lines(source, LineCountMode::Lines, chunk_size), lines);
+            assert_eq!(
+                count_lines(source, LineCountMode::Newlines, chunk_size),
+                newlines
+            );
+        }
+    }
+
+    /// Tests how line endings and unterminated last lines are counted in each
+    /// mode.
+    #[test]
+    fn test_line_endings() {
+        let cases = [
+            ("", 0, 0),
+            ("\n", 1, 1),
+            ("a", 1, 0),
+            ("a\n", 1, 1),
+            ("a\n\n", 2, 2),
+            ("a\r\nb\r\n", 2, 2),
+            ("a\rb\r", 2, 0),
+            ("a\r\rb", 3, 0),
+            ("a\nb", 2, 1),
+        ];
+
+        for (source, lines, newlines) in cases {
+            assert_eq!(
+                count_lines(source, LineCountMode::Lines, 1).lines,
+                lines,
+                "{source:?}"
+            );
+            assert_eq!(
+                count_lines(source, LineCountMode::Newlines, 1).lines,
+                newlines,
+                "{source:?}"
+            );
+        }
+    }
+}
@@ -1,13 +1,49 @@
 //! All application services.
 
+mod attributes;
+mod binary;
+mod cancel;
 mod code_stats;
+mod detect;
 mod dir_info;
+mod encoding;
+mod error;
 mod format;
+mod generated;
+mod heuristics;
 mod language;
-mod task_pool;
+mod lines;
+mod path_filters;
+mod progress;
+mod project_config;
+mod scan_cache;
+mod scan_error;
+mod scan_options;
+mod style;
+#[cfg(test)]
+mod test_dir;
+mod watch;
 
+pub use attributes::*;
+pub use binary::*;
+pub use cancel::*;
 pub use code_stats::*;
+pub use detect::*;
 pub use dir_info::*;
+pub use encoding::*;
+pub use error::*;
 pub use format::*;
+pub use generated::*;
+pub use heuristics::*;
 pub use language::*;
-pub use task_pool::*;
+pub use lines::*;
+pub use path_filters::*;
+pub use progress::*;
+pub use project_config::*;
+pub use scan_cache::*;
+pub use scan_error::*;
+pub use scan_options::*;
+pub use style::*;
+#[cfg(test)]
+pub use test_dir::*;
+pub use watch::*;
@@ -0,0 +1,187 @@
+//! Include and exclude glob filters for scans, saved per project.
+
+use super::CONFIG_DIR_NAME;
+use ignore::overrides::{Override, OverrideBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The name of the file in the user's configuration directory in which path
+/// filters are saved.
+const PATH_FILTERS_FILE_NAME: &str = "filters.toml";
+
+/// Glob patterns choosing which files within a project are scanned. Patterns
+/// use `.gitignore` syntax and are relative to the project root.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct PathFilters {
+    /// Patterns of the files to scan. When empty, all files are scanned.
+    pub include: Vec<String>,
+    /// Patterns of the files and directories to skip, taking precedence over
+    /// the include patterns.
+    pub exclude: Vec<String>,
+}
+
+impl PathFilters {
+    /// Checks whether there are no patterns.
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    /// Builds the walker overrides for the project at the given root.
+    pub fn overrides(&self, root: &Path) -> Result<Override, ignore::Error> {
+        let mut builder = OverrideBuilder::new(root);
+
+        for pattern in &self.include {
+            builder.add(pattern)?;
+        }
+
+        for pattern in &self.exclude {
+            builder.add(&format!("!{}", pattern))?;
+        }
+
+        builder.build()
+    }
+}
+
+/// The saved path filters of all projects, keyed by project root.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct SavedPathFilters {
+    /// The path filters of each project.
+    projects: BTreeMap<String, PathFilters>,
+}
+
+impl SavedPathFilters {
+    /// Reads the saved path filters from a file. A missing file contains no
+    /// filters.
+    fn read(file: &Path) -> io::Result<Self> {
+        match fs::read_to_string(file) {
+            Ok(source) => toml::from_str(&source)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err)),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Writes the saved path filters to a file, creating its directory if
+    /// needed.
+    fn write(&self, file: &Path) -> io::Result<()> {
+        let source =
+            toml::to_string(self).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        fs::write(file, source)
+    }
+}
+
+/// Gets the path to the file in which path filters are saved.
+fn path_filters_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(CONFIG_DIR_NAME).join(PATH_FILTERS_FILE_NAME))
+}
+
+/// Gets the key under which a project's path filters are saved.
+fn project_key(root: &Path) -> String {
+    root.to_string_lossy().into_owned()
+}
+
+/// Loads the path filters saved for the project at the given root. Projects
+/// without saved filters, or whose filters cannot be read, have no filters.
+pub fn load_path_filters(root: &Path) -> PathFilters {
+    path_filters_path()
+        .and_then(|file| SavedPathFilters::read(&file).ok())
+        .and_then(|mut saved| saved.projects.remove(&project_key(root)))
+        .unwrap_or_default()
+}
+
+/// Saves the path filters of the project at the given root. Empty filters
+/// remove the project's saved filters.
+pub fn save_path_filters(root: &Path, filters: &PathFilters) -> io::Result<()> {
+    let file = path_filters_path().ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::NotFound,
+            "no configuration directory is available",
+        )
+    })?;
+    let mut saved = SavedPathFilters::read(&file)?;
+
+    if filters.is_empty() {
+        saved.projects.remove(&project_key(root));
+    } else {
+        saved.projects.insert(project_key(root), filters.clone());
+    }
+
+    saved.write(&file)
+}
+
+/// Path filter tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::TestDir;
+
+    /// Checks whether the overrides skip a path.
+    fn skipped(overrides: &Override, path: &str, is_dir: bool) -> bool {
+        overrides
+            .matched(Path::new("/repo").join(path), is_dir)
+            .is_ignore()
+    }
+
+    /// Tests that include and exclude patterns choose the scanned files.
+    #[test]
+    fn test_overrides() {
+        let filters = PathFilters {
+            include: vec!["services/**".to_owned()],
+            exclude: vec!["third_party/".to_owned(), "**/testdata/**".to_owned()],
+        };
+        let overrides = filters.overrides(Path::new("/repo")).unwrap();
+
+        assert!(!skipped(&overrides, "services/api/main.go", false));
+        assert!(skipped(&overrides, "tools/gen.go", false));
+        assert!(!skipped(&overrides, "tools", true));
+        assert!(skipped(
+            &overrides,
+            "services/api/testdata/input.json",
+            false
+        ));
+        assert!(skipped(&overrides, "third_party", true));
+
+        let overrides = PathFilters::default()
+            .overrides(Path::new("/repo"))
+            .unwrap();
+        assert!(!skipped(&overrides, "tools/gen.go", false));
+
+        let filters = PathFilters {
+            include: vec!["src/[".to_owned()],
+            exclude: Vec::new(),
+        };
+        assert!(filters.overrides(Path::new("/repo")).is_err());
+    }
+
+    /// Tests that saved filters are read back.
+    #[test]
+    fn test_saved_filters() {
+        let dir = TestDir::new("filters");
+        let file = dir.join("filters.toml");
+        assert_eq!(
+            SavedPathFilters::read(&file).unwrap(),
+            SavedPathFilters::default()
+        );
+
+        let mut saved = SavedPathFilters::default();
+        saved.projects.insert(
+            "/repo".to_owned(),
+            PathFilters {
+                include: vec!["services/**".to_owned()],
+                exclude: vec!["third_party/".to_owned()],
+            },
+        );
+        saved.write(&file).unwrap();
+        assert_eq!(SavedPathFilters::read(&file).unwrap(), saved);
+    }
+}
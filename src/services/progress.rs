@@ -0,0 +1,137 @@
+//! Progress reporting for scans.
+
+use super::{CancellationToken, CodeStats};
+use ignore::WalkBuilder;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::watch;
+use tokio::task::spawn_blocking;
+
+/// The most entries a pre-count will walk before giving up, leaving the
+/// progress of a scan indeterminate.
+const PRE_COUNT_LIMIT: usize = 250_000;
+
+/// The progress of a scan in flight.
+#[derive(Debug, Clone)]
+pub struct ScanProgress {
+    /// The time the scan started.
+    pub started: Instant,
+    /// The number of directories walked.
+    pub dirs: usize,
+    /// The number of files counted.
+    pub files: usize,
+    /// The number of bytes read.
+    pub bytes: usize,
+    /// The path most recently counted.
+    pub current_path: Option<PathBuf>,
+    /// The number of files expected, if a pre-count has finished.
+    pub total_files: Option<usize>,
+    /// The statistics tallied so far, published periodically while the scan
+    /// is in progress.
+    pub partial: Option<Arc<CodeStats>>,
+}
+
+impl ScanProgress {
+    /// Creates the progress of a scan starting now.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            dirs: 0,
+            files: 0,
+            bytes: 0,
+            current_path: None,
+            total_files: None,
+            partial: None,
+        }
+    }
+
+    /// Gets the time since the scan started.
+    pub fn elapsed(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Gets the number of bytes read per second.
+    pub fn bytes_per_second(&self) -> f64 {
+        self.bytes as f64 / self.elapsed().as_secs_f64().max(0.001)
+    }
+
+    /// Gets the number of files counted per second.
+    pub fn files_per_second(&self) -> f64 {
+        self.files as f64 / self.elapsed().as_secs_f64().max(0.001)
+    }
+
+    /// Gets the fraction of the scan completed, if the number of files is
+    /// known. The pre-count does not apply project configuration, so it may
+    /// overestimate, and the fraction is capped below one.
+    pub fn fraction(&self) -> Option<f64> {
+        self.total_files
+            .filter(|&total| total > 0)
+            .map(|total| (self.files as f64 / total as f64).min(0.99))
+    }
+}
+
+impl Default for ScanProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The sending half of a progress channel, shared by the tasks of a scan.
+pub type ProgressSender = Arc<watch::Sender<ScanProgress>>;
+
+/// The receiving half of a progress channel.
+pub type ProgressReceiver = watch::Receiver<ScanProgress>;
+
+/// Creates a channel through which the latest progress of a scan can be
+/// observed.
+pub fn progress_channel() -> (ProgressSender, ProgressReceiver) {
+    let (sender, receiver) = watch::channel(ScanProgress::new());
+    (Arc::new(sender), receiver)
+}
+
+/// Quickly counts the files a walker will visit on a blocking thread, so that
+/// a scan can report determinate progress. Nothing is published if the count
+/// exceeds `PRE_COUNT_LIMIT` or the token is cancelled first.
+pub fn spawn_pre_count(walker: WalkBuilder, progress: ProgressSender, cancel: CancellationToken) {
+    spawn_blocking(move || {
+        let mut files = 0;
+
+        for (index, entry) in walker.build().enumerate() {
+            if index >= PRE_COUNT_LIMIT || cancel.is_cancelled() || progress.is_closed() {
+                return;
+            }
+
+            if let Ok(entry) = entry {
+                if entry
+                    .file_type()
+                    .is_some_and(|file_type| file_type.is_file())
+                {
+                    files += 1;
+                }
+            }
+        }
+
+        progress.send_modify(|progress| progress.total_files = Some(files));
+    });
+}
+
+/// Progress tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Tests the fraction of a scan completed.
+    #[test]
+    fn test_fraction() {
+        let mut progress = ScanProgress::new();
+        assert_eq!(progress.fraction(), None);
+
+        progress.total_files = Some(4);
+        progress.files = 1;
+        assert_eq!(progress.fraction(), Some(0.25));
+
+        progress.files = 5;
+        assert_eq!(progress.fraction(), Some(0.99));
+    }
+}
@@ -0,0 +1,461 @@
+//! Support for per-project `.code-stats.toml` configuration files.
+
+use super::{
+    CategorySelection, Language, LanguageCategory, LanguageDefinitions, LanguageEntry,
+    LanguageRegistry,
+};
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// The name of project configuration files.
+pub const PROJECT_CONFIG_FILE_NAME: &str = ".code-stats.toml";
+
+/// A named group of paths whose statistics are shown together.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ComponentEntry {
+    /// The name of the component.
+    name: String,
+    /// Patterns of the paths belonging to the component.
+    paths: Vec<String>,
+}
+
+/// The contents of a project configuration file.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+struct ProjectConfigFile {
+    /// Patterns of the files and directories to skip.
+    exclude: Vec<String>,
+    /// Languages to use for files matching patterns, by pattern.
+    overrides: BTreeMap<String, String>,
+    /// Component groupings.
+    #[serde(rename = "component")]
+    components: Vec<ComponentEntry>,
+    /// The language categories counted by default. Only read from the root
+    /// configuration file.
+    categories: Option<Vec<LanguageCategory>>,
+    /// Custom languages, in the same format as language definition files.
+    /// Only read from the root configuration file.
+    #[serde(rename = "language")]
+    languages: Vec<LanguageEntry>,
+}
+
+/// The rules of a single configuration file, applying to the files within its
+/// directory.
+#[derive(Debug, Clone)]
+struct ScopedConfig {
+    /// The matcher for excluded paths.
+    exclude: Gitignore,
+    /// Matchers for paths whose language is overridden, in file order.
+    overrides: Vec<(Gitignore, Language)>,
+    /// Matchers for the paths of each component, in file order.
+    components: Vec<(Gitignore, String)>,
+}
+
+/// Builds a matcher for a list of patterns relative to a directory.
+fn build_matcher<'a, I>(dir: &Path, patterns: I) -> Result<Gitignore, ignore::Error>
+where
+    I: IntoIterator<Item = &'a String>,
+{
+    let mut builder = GitignoreBuilder::new(dir);
+
+    for pattern in patterns {
+        builder.add_line(None, pattern)?;
+    }
+
+    builder.build()
+}
+
+impl ScopedConfig {
+    /// Compiles the rules of a configuration file in the given directory,
+    /// resolving language names with the registry.
+    fn new(
+        dir: &Path,
+        file: &ProjectConfigFile,
+        registry: &LanguageRegistry,
+    ) -> Result<Self, String> {
+        let exclude = build_matcher(dir, &file.exclude).map_err(|err| err.to_string())?;
+        let overrides = file
+            .overrides
+            .iter()
+            .map(|(pattern, name)| {
+                let language = registry
+                    .find_by_alias(name)
+                    .ok_or_else(|| format!("unknown language `{}` for `{}`", name, pattern))?;
+                let matcher = build_matcher(dir, [pattern]).map_err(|err| err.to_string())?;
+                Ok((matcher, language))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        let components = file
+            .components
+            .iter()
+            .map(|component| {
+                let matcher =
+                    build_matcher(dir, &component.paths).map_err(|err| err.to_string())?;
+                Ok((matcher, component.name.clone()))
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+
+        Ok(Self {
+            exclude,
+            overrides,
+            components,
+        })
+    }
+}
+
+/// Reads and parses the configuration file in a directory, if it has one.
+fn read_config_file(dir: &Path) -> Option<Result<ProjectConfigFile, String>> {
+    match fs::read_to_string(dir.join(PROJECT_CONFIG_FILE_NAME)) {
+        Ok(source) => Some(toml::from_str(&source).map_err(|err| err.to_string())),
+        Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+        Err(err) => Some(Err(err.to_string())),
+    }
+}
+
+/// A summary of the project configuration applied to a scan.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct ProjectConfigSummary {
+    /// The configuration files that were loaded, relative to the root.
+    pub files: Vec<PathBuf>,
+    /// Errors encountered while loading configuration files, each prefixed by
+    /// the file's path relative to the root.
+    pub errors: Vec<String>,
+    /// The language categories counted by default, if the root configuration
+    /// file chooses them.
+    pub categories: Option<CategorySelection>,
+}
+
+/// The project configuration of a directory tree, loaded from the
+/// `.code-stats.toml` files of each directory as it is walked. Like
+/// `.gitignore` files, each file applies to its own directory and below, and
+/// deeper files take precedence.
+#[derive(Debug, Clone)]
+pub struct ProjectConfigs {
+    /// The root directory of the tree.
+    root: PathBuf,
+    /// The registry used to resolve language names, including the custom
+    /// languages of the root configuration file.
+    registry: Arc<LanguageRegistry>,
+    /// The rules of the configuration file in each directory that has one.
+    configs: HashMap<PathBuf, ScopedConfig>,
+    /// A summary of the loaded configuration.
+    summary: ProjectConfigSummary,
+}
+
+impl ProjectConfigs {
+    /// Loads the configuration file at the root of the tree, if there is one.
+    /// Custom languages defined at the root extend the given registry.
+    pub fn new(root: &Path, registry: Arc<LanguageRegistry>) -> Self {
+        let mut configs = Self {
+            root: root.to_path_buf(),
+            registry,
+            configs: HashMap::new(),
+            summary: ProjectConfigSummary::default(),
+        };
+
+        match read_config_file(root) {
+            Some(Ok(file)) => {
+                if !file.languages.is_empty() {
+                    configs.registry = Arc::new(LanguageRegistry::with_project_definitions(
+                        LanguageDefinitions {
+                            languages: file.languages.clone(),
+                        },
+                    ));
+                }
+
+                configs.summary.categories = file
+                    .categories
+                    .as_ref()
+                    .map(|categories| categories.iter().copied().collect());
+                configs.insert(root, &file);
+            }
+            Some(Err(err)) => configs.add_error(root, err),
+            None => {}
+        }
+
+        configs
+    }
+
+    /// Gets the registry used to detect languages within the tree.
+    pub fn registry(&self) -> Arc<LanguageRegistry> {
+        self.registry.clone()
+    }
+
+    /// Gets a summary of the configuration loaded so far.
+    pub fn summary(&self) -> &ProjectConfigSummary {
+        &self.summary
+    }
+
+    /// Loads the configuration file of a directory, if it has one. Directories
+    /// must be loaded before the files within them are checked.
+    pub fn load_dir(&mut self, dir: &Path) {
+        if dir == self.root {
+            return;
+        }
+
+        match read_config_file(dir) {
+            Some(Ok(file)) => {
+                if file.categories.is_some() || !file.languages.is_empty() {
+                    self.add_error(
+                        dir,
+                        "`categories` and `language` are only read from the root configuration file"
+                            .to_owned(),
+                    );
+                }
+
+                self.insert(dir, &file);
+            }
+            Some(Err(err)) => self.add_error(dir, err),
+            None => {}
+        }
+    }
+
+    /// Compiles and stores the rules of a configuration file.
+    fn insert(&mut self, dir: &Path, file: &ProjectConfigFile) {
+        match ScopedConfig::new(dir, file, &self.registry) {
+            Ok(config) => {
+                self.summary.files.push(self.relative_config_path(dir));
+                self.configs.insert(dir.to_path_buf(), config);
+            }
+            Err(err) => self.add_error(dir, err),
+        }
+    }
+
+    /// Records an error in the configuration file of a directory.
+    fn add_error(&mut self, dir: &Path, err: String) {
+        let path = self.relative_config_path(dir);
+        self.summary
+            .errors
+            .push(format!("{}: {}", path.display(), err));
+    }
+
+    /// Gets the path of a directory's configuration file relative to the root.
+    fn relative_config_path(&self, dir: &Path) -> PathBuf {
+        dir.strip_prefix(&self.root)
+            .unwrap_or(dir)
+            .join(PROJECT_CONFIG_FILE_NAME)
+    }
+
+    /// Gets the rules that apply to a path, from the shallowest directory to
+    /// the deepest.
+    fn scoped_configs<'a>(&'a self, path: &'a Path) -> impl Iterator<Item = &'a ScopedConfig> {
+        let mut dirs = path
+            .ancestors()
+            .skip(1)
+            .take_while(|dir| dir.starts_with(&self.root))
+            .collect::<Vec<_>>();
+        dirs.reverse();
+
+        dirs.into_iter().filter_map(|dir| self.configs.get(dir))
+    }
+
+    /// Checks whether a path is excluded by any configuration file.
+    pub fn is_excluded(&self, path: &Path, is_dir: bool) -> bool {
+        self.scoped_configs(path).any(|config| {
+            config
+                .exclude
+                .matched_path_or_any_parents(path, is_dir)
+                .is_ignore()
+        })
+    }
+
+    /// Gets the language configured for a file, if any. Deeper configuration
+    /// files take precedence, as do later patterns within a file.
+    pub fn language_override(&self, path: &Path) -> Option<Language> {
+        self.scoped_configs(path)
+            .flat_map(|config| &config.overrides)
+            .filter(|(matcher, _)| matcher.matched_path_or_any_parents(path, false).is_ignore())
+            .last()
+            .map(|(_, language)| language.clone())
+    }
+
+    /// Gets the component a file belongs to, if any. Deeper configuration
+    /// files take precedence, as do earlier components within a file.
+    pub fn component(&self, path: &Path) -> Option<String> {
+        self.scoped_configs(path)
+            .filter_map(|config| {
+                config.components.iter().find(|(matcher, _)| {
+                    matcher.matched_path_or_any_parents(path, false).is_ignore()
+                })
+            })
+            .last()
+            .map(|(_, name)| name.clone())
+    }
+}
+
+/// Project configuration tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::TestDir;
+
+    /// Creates a set of configurations from the contents of configuration
+    /// files in the given directories, the first of which is the root.
+    fn configs(files: &[(&str, &str)]) -> ProjectConfigs {
+        let root = Path::new("/repo");
+        let mut configs = ProjectConfigs {
+            root: root.to_path_buf(),
+            registry: Arc::new(LanguageRegistry::builtin()),
+            configs: HashMap::new(),
+            summary: ProjectConfigSummary::default(),
+        };
+
+        for (dir, source) in files {
+            let dir = root.join(dir);
+            match toml::from_str(source) {
+                Ok(file) => configs.insert(&dir, &file),
+                Err(err) => configs.add_error(&dir, err.to_string()),
+            }
+        }
+
+        configs
+    }
+
+    /// Tests that configuration files apply to their own directories.
+    #[test]
+    fn test_scoped_rules() {
+        let configs = configs(&[
+            (
+                "",
+                r#"
+                exclude = ["third_party/", "**/testdata/**"]
+
+                [overrides]
+                "*.inc" = "PHP"
+
+                [[component]]
+                name = "backend"
+                paths = ["services/"]
+
+                [[component]]
+                name = "frontend"
+                paths = ["web/**"]
+                "#,
+            ),
+            (
+                "services/legacy",
+                r#"
+                exclude = ["old.c"]
+
+                [overrides]
+                "*.inc" = "Pascal"
+
+                [[component]]
+                name = "legacy"
+                paths = ["*"]
+                "#,
+            ),
+        ]);
+        let path = |path: &str| Path::new("/repo").join(path);
+        let language = |path_str: &str| {
+            configs
+                .language_override(&path(path_str))
+                .map(|language| language.name().to_owned())
+        };
+        let component = |path_str: &str| configs.component(&path(path_str));
+
+        assert!(configs.is_excluded(&path("third_party"), true));
+        assert!(configs.is_excluded(&path("third_party/lib/x.c"), false));
+        assert!(configs.is_excluded(&path("services/api/testdata/in.json"), false));
+        assert!(configs.is_excluded(&path("services/legacy/old.c"), false));
+        assert!(!configs.is_excluded(&path("old.c"), false));
+
+        assert_eq!(language("lib/header.inc").as_deref(), Some("PHP"));
+        assert_eq!(
+            language("services/legacy/header.inc").as_deref(),
+            Some("Pascal")
+        );
+        assert_eq!(language("main.c"), None);
+
+        assert_eq!(
+            component("services/api/main.go").as_deref(),
+            Some("backend")
+        );
+        assert_eq!(
+            component("services/legacy/main.c").as_deref(),
+            Some("legacy")
+        );
+        assert_eq!(component("web/src/app.ts").as_deref(), Some("frontend"));
+        assert_eq!(component("README.md"), None);
+
+        assert_eq!(
+            configs.summary().files,
+            [
+                PathBuf::from(".code-stats.toml"),
+                PathBuf::from("services/legacy/.code-stats.toml")
+            ]
+        );
+        assert!(configs.summary().errors.is_empty());
+    }
+
+    /// Tests that custom languages and categories are read from the root
+    /// configuration file.
+    #[test]
+    fn test_root_config() {
+        let root = TestDir::new("project-config");
+        fs::create_dir_all(root.join("sub")).unwrap();
+        fs::write(
+            root.join(PROJECT_CONFIG_FILE_NAME),
+            r#"
+            categories = ["programming", "data"]
+
+            [overrides]
+            "*.acme" = "Acme Config"
+
+            [[language]]
+            name = "Acme Config"
+            category = "data"
+            extensions = ["acmecfg"]
+            "#,
+        )
+        .unwrap();
+        fs::write(
+            root.join("sub").join(PROJECT_CONFIG_FILE_NAME),
+            "categories = [\"prose\"]\n",
+        )
+        .unwrap();
+
+        let mut configs = ProjectConfigs::new(&root, Arc::new(LanguageRegistry::builtin()));
+        configs.load_dir(&root);
+        configs.load_dir(&root.join("sub"));
+
+        assert!(configs.registry().find_by_alias("acme config").is_some());
+        assert_eq!(
+            configs
+                .language_override(&root.join("sub/app.acme"))
+                .map(|language| language.name().to_owned())
+                .as_deref(),
+            Some("Acme Config")
+        );
+        assert_eq!(
+            configs.summary().categories,
+            Some(
+                [LanguageCategory::Programming, LanguageCategory::Data]
+                    .into_iter()
+                    .collect()
+            )
+        );
+        assert_eq!(configs.summary().files.len(), 2);
+        assert_eq!(configs.summary().errors.len(), 1);
+    }
+
+    /// Tests that invalid configuration files are reported.
+    #[test]
+    fn test_errors() {
+        let configs = configs(&[
+            ("", "exclude = 1\n"),
+            ("lib", "[overrides]\n\"*.inc\" = \"Not A Language\"\n"),
+        ]);
+
+        assert!(configs.summary().files.is_empty());
+        assert_eq!(configs.summary().errors.len(), 2);
+        assert!(configs.summary().errors[0].starts_with(".code-stats.toml: "));
+        assert!(configs.summary().errors[1].contains("unknown language `Not A Language`"));
+    }
+}
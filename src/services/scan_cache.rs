@@ -0,0 +1,382 @@
+//! A cache of the statistics of files from earlier scans, saved per project,
+//! so that rescans only read the files that changed.
+
+use super::{
+    FileCounts, FileStats, FileStyle, FileTags, Language, LanguageRegistry, LineCountMode,
+    MediaType, ScanOptions, TextEncoding, CONFIG_DIR_NAME,
+};
+use fnv::FnvHasher;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::hash::Hasher;
+use std::io::{self, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::UNIX_EPOCH;
+
+/// The name of the directory within the user's cache directory in which scan
+/// caches are saved.
+const SCAN_CACHE_DIR_NAME: &str = "scans";
+
+/// The metadata of a file used to decide whether it changed since it was
+/// cached.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    /// The size of the file in bytes.
+    size: u64,
+    /// The modification time, in seconds and nanoseconds since the epoch.
+    modified: Option<(u64, u32)>,
+    /// The inode number, or zero where inodes are not available.
+    inode: u64,
+}
+
+impl FileFingerprint {
+    /// Gets the fingerprint of a file from its metadata.
+    pub fn new(metadata: &fs::Metadata) -> Self {
+        #[cfg(unix)]
+        let inode = std::os::unix::fs::MetadataExt::ino(metadata);
+        #[cfg(not(unix))]
+        let inode = 0;
+
+        Self {
+            size: metadata.len(),
+            modified: metadata
+                .modified()
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map(|modified| (modified.as_secs(), modified.subsec_nanos())),
+            inode,
+        }
+    }
+}
+
+/// The cached statistics of a single file, as detected from its content before
+/// attributes and project configuration are applied.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CachedFile {
+    /// The fingerprint of the file when it was read.
+    fingerprint: FileFingerprint,
+    /// The name of the language the file was forced to, if any.
+    language_override: Option<String>,
+    /// The name of the detected language.
+    language: String,
+    /// The media type of the file, if it is binary.
+    media_type: Option<MediaType>,
+    /// The encoding of the file, if it is text.
+    encoding: Option<TextEncoding>,
+    /// The line ending and whitespace style of the file, if it is text.
+    style: Option<FileStyle>,
+    /// Whether the file was detected as generated.
+    generated: bool,
+    /// The tallied statistics.
+    counts: FileCounts,
+}
+
+impl CachedFile {
+    /// Creates a cache entry for the statistics of a file.
+    pub fn new(
+        fingerprint: FileFingerprint,
+        language_override: Option<&Language>,
+        stats: &FileStats,
+    ) -> Self {
+        Self {
+            fingerprint,
+            language_override: language_override.map(|language| language.name().to_owned()),
+            language: stats.language.name().to_owned(),
+            media_type: stats.media_type,
+            encoding: stats.encoding,
+            style: stats.style,
+            generated: stats.tags.generated,
+            counts: stats.counts,
+        }
+    }
+
+    /// Restores the statistics of the file, or `None` if its language no
+    /// longer exists.
+    pub fn stats(&self, registry: &LanguageRegistry) -> Option<FileStats> {
+        Some(FileStats {
+            language: registry.find_by_name(&self.language)?,
+            media_type: self.media_type,
+            encoding: self.encoding,
+            style: self.style,
+            tags: FileTags {
+                generated: self.generated,
+                ..FileTags::default()
+            },
+            component: None,
+            counts: self.counts,
+        })
+    }
+}
+
+/// The contents of a scan cache file.
+#[derive(Debug, Clone, PartialEq, Default, Serialize, Deserialize)]
+struct ScanCacheFile {
+    /// The version of the application that wrote the cache, since detection
+    /// may change between versions.
+    version: String,
+    /// The root of the project.
+    root: PathBuf,
+    /// The fingerprint of the language registry used to detect languages.
+    registry: u64,
+    /// The size above which only the start of files was read.
+    read_limit: Option<u64>,
+    /// How the lines of files were counted.
+    line_count_mode: LineCountMode,
+    /// The cached files, by full path.
+    files: HashMap<PathBuf, CachedFile>,
+}
+
+/// A cache of file statistics for a single project.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScanCache {
+    /// The file the cache is saved to.
+    file: PathBuf,
+    /// The cached contents.
+    contents: ScanCacheFile,
+}
+
+impl ScanCache {
+    /// Creates an empty cache for a project scanned with the given options,
+    /// to be saved to the given file.
+    pub fn new(
+        file: PathBuf,
+        root: &Path,
+        registry: &LanguageRegistry,
+        options: &ScanOptions,
+    ) -> Self {
+        Self {
+            file,
+            contents: ScanCacheFile {
+                version: env!("CARGO_PKG_VERSION").to_owned(),
+                root: root.to_path_buf(),
+                registry: registry.fingerprint(),
+                read_limit: options.read_limit,
+                line_count_mode: options.line_count_mode,
+                files: HashMap::new(),
+            },
+        }
+    }
+
+    /// Loads the cache of a project from a file. A missing or unreadable file,
+    /// or one written by another version, with other language definitions or
+    /// with options that change how files are counted, yields an empty cache.
+    pub fn load(
+        file: PathBuf,
+        root: &Path,
+        registry: &LanguageRegistry,
+        options: &ScanOptions,
+    ) -> Self {
+        let mut cache = Self::new(file, root, registry, options);
+
+        if let Ok(contents) = File::open(&cache.file)
+            .map(BufReader::new)
+            .map_err(bincode::Error::from)
+            .and_then(bincode::deserialize_from::<_, ScanCacheFile>)
+        {
+            if contents.version == cache.contents.version
+                && contents.root == cache.contents.root
+                && contents.registry == cache.contents.registry
+                && contents.read_limit == cache.contents.read_limit
+                && contents.line_count_mode == cache.contents.line_count_mode
+            {
+                cache.contents.files = contents.files;
+            }
+        }
+
+        cache
+    }
+
+    /// Creates an empty cache to be saved to the same file as this one.
+    pub fn empty_copy(&self) -> Self {
+        Self {
+            file: self.file.clone(),
+            contents: ScanCacheFile {
+                files: HashMap::new(),
+                ..self.contents.clone()
+            },
+        }
+    }
+
+    /// Gets the cached statistics of a file, if the file has not changed and
+    /// was cached with the same language override.
+    pub fn get(
+        &self,
+        path: &Path,
+        fingerprint: FileFingerprint,
+        language_override: Option<&Language>,
+    ) -> Option<&CachedFile> {
+        self.contents.files.get(path).filter(|cached| {
+            cached.fingerprint == fingerprint
+                && cached.language_override.as_deref() == language_override.map(Language::name)
+        })
+    }
+
+    /// Adds the statistics of a file to the cache.
+    pub fn insert(&mut self, path: PathBuf, cached: CachedFile) {
+        self.contents.files.insert(path, cached);
+    }
+
+    /// Saves the cache, replacing the file atomically. The cache is written
+    /// to a temporary file unique to this save first, so that concurrent
+    /// saves of the same project never publish each other's partial writes.
+    pub fn save(&self) -> io::Result<()> {
+        static SAVE_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+        if let Some(dir) = self.file.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let temp_file = self.file.with_extension(format!(
+            "{}.{}.tmp",
+            process::id(),
+            SAVE_COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let result = File::create(&temp_file).and_then(|file| {
+            let mut writer = BufWriter::new(file);
+            bincode::serialize_into(&mut writer, &self.contents).map_err(io::Error::other)?;
+            writer.flush()?;
+            fs::rename(&temp_file, &self.file)
+        });
+
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_file);
+        }
+
+        result
+    }
+}
+
+/// Gets the path to the scan cache file of a project.
+pub fn scan_cache_path(root: &Path) -> Option<PathBuf> {
+    let mut hasher = FnvHasher::default();
+    hasher.write(root.as_os_str().as_encoded_bytes());
+    let name = format!("{:016x}.bin", hasher.finish());
+
+    dirs::cache_dir().map(|dir| {
+        dir.join(CONFIG_DIR_NAME)
+            .join(SCAN_CACHE_DIR_NAME)
+            .join(name)
+    })
+}
+
+/// Scan cache tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::TestDir;
+
+    /// Tests that cached files are reused only while they are unchanged.
+    #[test]
+    fn test_scan_cache() {
+        let dir = TestDir::new("scan-cache");
+        let source = dir.join("main.rs");
+        fs::write(&source, "fn main() {}\n").unwrap();
+
+        let registry = LanguageRegistry::builtin();
+        let options = ScanOptions::default();
+        let rust = registry.find_by_alias("rust").unwrap();
+        let stats = FileStats {
+            language: rust.clone(),
+            media_type: None,
+            encoding: Some(TextEncoding::Utf8),
+            style: Some(FileStyle {
+                lf_lines: 1,
+                ..FileStyle::default()
+            }),
+            tags: FileTags::default(),
+            component: None,
+            counts: FileCounts {
+                lines: 1,
+                code: 1,
+                bytes: 13,
+                ..FileCounts::default()
+            },
+        };
+        let fingerprint = FileFingerprint::new(&fs::metadata(&source).unwrap());
+
+        let cache_file = dir.join("cache.bin");
+        let mut cache = ScanCache::new(cache_file.clone(), &dir, &registry, &options);
+        cache.insert(source.clone(), CachedFile::new(fingerprint, None, &stats));
+        cache.save().unwrap();
+
+        let cache = ScanCache::load(cache_file.clone(), &dir, &registry, &options);
+        let cached = cache.get(&source, fingerprint, None).unwrap();
+        assert_eq!(cached.stats(&registry), Some(stats));
+        assert!(cache.get(&source, fingerprint, Some(&rust)).is_none());
+
+        fs::write(&source, "fn main() {}\n\n").unwrap();
+        let fingerprint = FileFingerprint::new(&fs::metadata(&source).unwrap());
+        assert!(cache.get(&source, fingerprint, None).is_none());
+
+        let limited = ScanOptions {
+            read_limit: Some(1024),
+            ..ScanOptions::default()
+        };
+        let cache = ScanCache::load(cache_file.clone(), &dir, &registry, &limited);
+        assert!(cache.contents.files.is_empty());
+
+        let newlines = ScanOptions {
+            line_count_mode: LineCountMode::Newlines,
+            ..ScanOptions::default()
+        };
+        let cache = ScanCache::load(cache_file.clone(), &dir, &registry, &newlines);
+        assert!(cache.contents.files.is_empty());
+
+        let cache = ScanCache::load(cache_file, Path::new("/other"), &registry, &options);
+        assert!(cache.contents.files.is_empty());
+    }
+
+    /// Tests that concurrent saves of the same cache never leave a partially
+    /// written file or temporary files behind.
+    #[test]
+    fn test_concurrent_saves() {
+        let dir = TestDir::new("scan-saves");
+
+        let registry = LanguageRegistry::builtin();
+        let options = ScanOptions::default();
+        let cache_file = dir.join("cache.bin");
+        let mut cache = ScanCache::new(cache_file.clone(), &dir, &registry, &options);
+        let rust = registry.find_by_alias("rust").unwrap();
+        let stats = FileStats {
+            language: rust,
+            media_type: None,
+            encoding: Some(TextEncoding::Utf8),
+            style: None,
+            tags: FileTags::default(),
+            component: None,
+            counts: FileCounts::default(),
+        };
+
+        for index in 0..1000 {
+            let path = dir.join(format!("{index}.rs"));
+            let fingerprint = FileFingerprint::new(&fs::metadata(&dir).unwrap());
+            cache.insert(path, CachedFile::new(fingerprint, None, &stats));
+        }
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    for _ in 0..10 {
+                        cache.save().unwrap();
+                    }
+                });
+            }
+        });
+
+        let loaded = ScanCache::load(cache_file.clone(), &dir, &registry, &options);
+        assert_eq!(loaded.contents, cache.contents);
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+    }
+
+    /// Tests that cache file names do not depend on the toolchain's default
+    /// hasher.
+    #[test]
+    fn test_scan_cache_path() {
+        if let Some(path) = scan_cache_path(Path::new("/repo")) {
+            assert_eq!(path.file_name().unwrap(), "f7556aa5a4543342.bin");
+        }
+    }
+}
@@ -0,0 +1,165 @@
+//! Errors encountered while scanning individual paths.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The reason a path was skipped during a scan.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ScanErrorKind {
+    /// The path could not be read due to its permissions.
+    PermissionDenied,
+    /// The path disappeared during the scan.
+    NotFound,
+    /// The path is a symbolic link whose target does not exist.
+    BrokenSymlink,
+    /// The path is a symbolic link to one of its own ancestors.
+    Loop,
+    /// Any other I/O error.
+    Io,
+}
+
+impl ScanErrorKind {
+    /// Gets the display name of the reason.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::PermissionDenied => "permission denied",
+            Self::NotFound => "not found",
+            Self::BrokenSymlink => "broken symbolic link",
+            Self::Loop => "symbolic link loop",
+            Self::Io => "I/O error",
+        }
+    }
+}
+
+/// A path that was skipped during a scan, and the reason why.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanError {
+    /// The full path that was skipped.
+    pub path: PathBuf,
+    /// The reason the path was skipped.
+    pub kind: ScanErrorKind,
+    /// A description of the underlying error.
+    pub message: String,
+}
+
+impl ScanError {
+    /// Creates an error for a path that could not be read.
+    pub fn from_io(path: &Path, err: &io::Error) -> Self {
+        let kind = match err.kind() {
+            io::ErrorKind::PermissionDenied => ScanErrorKind::PermissionDenied,
+            io::ErrorKind::NotFound if is_symlink(path) => ScanErrorKind::BrokenSymlink,
+            io::ErrorKind::NotFound => ScanErrorKind::NotFound,
+            _ => ScanErrorKind::Io,
+        };
+
+        Self {
+            path: path.to_path_buf(),
+            kind,
+            message: err.to_string(),
+        }
+    }
+
+    /// Creates an error from an error reported by the directory walker. Errors
+    /// that are not associated with a path are reported against the root.
+    pub fn from_walk(root: &Path, err: &ignore::Error) -> Self {
+        match err {
+            ignore::Error::Loop { child, .. } => Self {
+                path: child.clone(),
+                kind: ScanErrorKind::Loop,
+                message: err.to_string(),
+            },
+            ignore::Error::WithPath { path, err: inner } => match inner.io_error() {
+                Some(io_err) => Self {
+                    message: err.to_string(),
+                    ..Self::from_io(path, io_err)
+                },
+                None => Self {
+                    path: path.clone(),
+                    ..Self::from_walk(root, inner)
+                },
+            },
+            ignore::Error::WithDepth { err, .. } | ignore::Error::WithLineNumber { err, .. } => {
+                Self::from_walk(root, err)
+            }
+            _ => match err.io_error() {
+                Some(io_err) => Self {
+                    message: err.to_string(),
+                    ..Self::from_io(root, io_err)
+                },
+                None => Self {
+                    path: root.to_path_buf(),
+                    kind: ScanErrorKind::Io,
+                    message: err.to_string(),
+                },
+            },
+        }
+    }
+}
+
+/// Checks whether a path is a symbolic link, without following it.
+fn is_symlink(path: &Path) -> bool {
+    fs::symlink_metadata(path).is_ok_and(|metadata| metadata.file_type().is_symlink())
+}
+
+/// Scan error tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::TestDir;
+
+    /// Tests that I/O errors are classified by kind.
+    #[test]
+    fn test_from_io() {
+        let path = Path::new("/nonexistent/code-stats/file.rs");
+        let kind = |kind| ScanError::from_io(path, &io::Error::from(kind)).kind;
+
+        assert_eq!(
+            kind(io::ErrorKind::PermissionDenied),
+            ScanErrorKind::PermissionDenied
+        );
+        assert_eq!(kind(io::ErrorKind::NotFound), ScanErrorKind::NotFound);
+        assert_eq!(kind(io::ErrorKind::InvalidData), ScanErrorKind::Io);
+    }
+
+    /// Tests that walker errors keep the path they occurred at.
+    #[test]
+    fn test_from_walk() {
+        let root = Path::new("/repo");
+        let err = ignore::Error::WithDepth {
+            depth: 2,
+            err: Box::new(ignore::Error::WithPath {
+                path: PathBuf::from("/repo/private"),
+                err: Box::new(ignore::Error::Io(io::Error::from(
+                    io::ErrorKind::PermissionDenied,
+                ))),
+            }),
+        };
+        let scan_error = ScanError::from_walk(root, &err);
+        assert_eq!(scan_error.path, Path::new("/repo/private"));
+        assert_eq!(scan_error.kind, ScanErrorKind::PermissionDenied);
+
+        let err = ignore::Error::Loop {
+            ancestor: PathBuf::from("/repo"),
+            child: PathBuf::from("/repo/link"),
+        };
+        let scan_error = ScanError::from_walk(root, &err);
+        assert_eq!(scan_error.path, Path::new("/repo/link"));
+        assert_eq!(scan_error.kind, ScanErrorKind::Loop);
+    }
+
+    /// Tests that a missing link target is reported as a broken link.
+    #[cfg(unix)]
+    #[test]
+    fn test_broken_symlink() {
+        let dir = TestDir::new("scan-error");
+        let link = dir.join("link.rs");
+        std::os::unix::fs::symlink(dir.join("missing.rs"), &link).unwrap();
+
+        let err = fs::read(&link).unwrap_err();
+        assert_eq!(
+            ScanError::from_io(&link, &err).kind,
+            ScanErrorKind::BrokenSymlink
+        );
+    }
+}
@@ -0,0 +1,149 @@
+//! Options controlling which files are scanned.
+
+use super::LineCountMode;
+use ignore::WalkBuilder;
+use std::path::Path;
+
+/// The name of the ignore file read in addition to `.gitignore` files.
+pub const DEFAULT_IGNORE_FILE_NAME: &str = ".codestatsignore";
+
+/// Options controlling how a directory tree is walked when collecting
+/// statistics. Each walk option maps onto the `ignore::WalkBuilder` option of
+/// the same name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScanOptions {
+    /// Whether hidden files and directories are skipped.
+    pub hidden: bool,
+    /// Whether `.gitignore` files are respected.
+    pub git_ignore: bool,
+    /// Whether the global git ignore file is respected.
+    pub git_global: bool,
+    /// Whether `.git/info/exclude` is respected.
+    pub git_exclude: bool,
+    /// The maximum depth to descend to, where the root is at depth zero.
+    pub max_depth: Option<usize>,
+    /// The size, in bytes, above which files are skipped.
+    pub max_filesize: Option<u64>,
+    /// The size, in bytes, above which only the start of a file, up to this
+    /// size, is read. The bytes of such files are counted in full, but their
+    /// lines only within the part read. A limit of 0 reads whole files.
+    pub read_limit: Option<u64>,
+    /// How the lines of files are counted.
+    pub line_count_mode: LineCountMode,
+    /// Whether to stay on the file system of the root.
+    pub same_file_system: bool,
+    /// Whether symbolic links are followed.
+    pub follow_links: bool,
+    /// The names of additional ignore files, using `.gitignore` syntax.
+    pub ignore_file_names: Vec<String>,
+    /// The number of threads that walk the tree and count files, where zero
+    /// picks a number based on the available CPUs.
+    pub threads: usize,
+    /// Whether the statistics of unchanged files are reused from the previous
+    /// scan of the same directory.
+    pub cache: bool,
+}
+
+impl Default for ScanOptions {
+    fn default() -> Self {
+        Self {
+            hidden: true,
+            git_ignore: true,
+            git_global: true,
+            git_exclude: true,
+            max_depth: None,
+            max_filesize: None,
+            read_limit: None,
+            line_count_mode: LineCountMode::default(),
+            same_file_system: false,
+            follow_links: false,
+            ignore_file_names: vec![DEFAULT_IGNORE_FILE_NAME.to_owned()],
+            threads: 0,
+            cache: true,
+        }
+    }
+}
+
+impl ScanOptions {
+    /// Creates a directory walker for the given root with these options.
+    pub fn walk_builder(&self, root: &Path) -> WalkBuilder {
+        let mut builder = WalkBuilder::new(root);
+        builder
+            .hidden(self.hidden)
+            .git_ignore(self.git_ignore)
+            .git_global(self.git_global)
+            .git_exclude(self.git_exclude)
+            .max_depth(self.max_depth)
+            .max_filesize(self.max_filesize)
+            .same_file_system(self.same_file_system)
+            .follow_links(self.follow_links)
+            .threads(self.threads);
+
+        for name in &self.ignore_file_names {
+            builder.add_custom_ignore_filename(name);
+        }
+
+        builder
+    }
+}
+
+/// Scan options tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::services::TestDir;
+    use std::fs;
+    use std::path::PathBuf;
+
+    /// Walks a tree with the given options, returning the relative paths of
+    /// the files found.
+    fn walk(root: &Path, options: &ScanOptions) -> Vec<PathBuf> {
+        let mut files = options
+            .walk_builder(root)
+            .build()
+            .flatten()
+            .filter(|entry| entry.path().is_file())
+            .map(|entry| entry.path().strip_prefix(root).unwrap().to_path_buf())
+            .collect::<Vec<_>>();
+        files.sort();
+        files
+    }
+
+    /// Tests that the options are applied to the walk.
+    #[test]
+    fn test_walk_builder() {
+        let root = TestDir::new("scan-options");
+        fs::create_dir_all(root.join("src/nested")).unwrap();
+        fs::write(root.join("main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join(".hidden.rs"), "").unwrap();
+        fs::write(root.join("src/lib.rs"), "").unwrap();
+        fs::write(root.join("src/nested/big.rs"), "x".repeat(1024)).unwrap();
+        fs::write(root.join("fixtures.json"), "{}").unwrap();
+        fs::write(root.join(DEFAULT_IGNORE_FILE_NAME), "*.json\n").unwrap();
+
+        let paths = |paths: &[&str]| paths.iter().map(PathBuf::from).collect::<Vec<_>>();
+        let options = ScanOptions::default();
+        assert_eq!(
+            walk(&root, &options),
+            paths(&["main.rs", "src/lib.rs", "src/nested/big.rs"])
+        );
+
+        let options = ScanOptions {
+            hidden: false,
+            ignore_file_names: Vec::new(),
+            max_depth: Some(2),
+            max_filesize: Some(512),
+            ..ScanOptions::default()
+        };
+        assert_eq!(
+            walk(&root, &options),
+            paths(&[
+                ".codestatsignore",
+                ".hidden.rs",
+                "fixtures.json",
+                "main.rs",
+                "src/lib.rs"
+            ])
+        );
+    }
+}
@@ -0,0 +1,488 @@
+//! Line ending and whitespace style detection.
+
+use memchr::memchr2;
+use serde::{Deserialize, Serialize};
+use std::mem;
+
+/// The largest indentation step detected as an indentation width.
+const MAX_INDENT_WIDTH: usize = 8;
+
+/// The style of the line endings of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum LineEndingStyle {
+    /// The file has no line endings.
+    None,
+    /// Every line ends in a line feed.
+    Lf,
+    /// Every line ends in a carriage return followed by a line feed.
+    Crlf,
+    /// Every line ends in a lone carriage return.
+    Cr,
+    /// Lines end in more than one way.
+    Mixed,
+}
+
+impl LineEndingStyle {
+    /// Gets the name of the style.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Lf => "LF",
+            Self::Crlf => "CRLF",
+            Self::Cr => "CR",
+            Self::Mixed => "Mixed",
+        }
+    }
+}
+
+/// The style of the indentation of a file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum IndentStyle {
+    /// No line is indented.
+    None,
+    /// Lines are indented with tabs only.
+    Tabs,
+    /// Lines are indented with spaces only.
+    Spaces,
+    /// Lines are indented with both tabs and spaces.
+    Mixed,
+}
+
+impl IndentStyle {
+    /// Gets the name of the style.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::None => "None",
+            Self::Tabs => "Tabs",
+            Self::Spaces => "Spaces",
+            Self::Mixed => "Mixed",
+        }
+    }
+}
+
+/// The line ending and whitespace style of a text file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct FileStyle {
+    /// The number of lines ending in a line feed.
+    pub lf_lines: usize,
+    /// The number of lines ending in a carriage return and a line feed.
+    pub crlf_lines: usize,
+    /// The number of lines ending in a lone carriage return.
+    pub cr_lines: usize,
+    /// The number of lines indented with tabs only.
+    pub tab_indented_lines: usize,
+    /// The number of lines indented with spaces only.
+    pub space_indented_lines: usize,
+    /// The number of lines indented with both tabs and spaces.
+    pub mixed_indented_lines: usize,
+    /// The most common step between the indentation of consecutive lines
+    /// indented with spaces, if any.
+    pub indent_width: Option<usize>,
+    /// The number of lines ending in spaces or tabs.
+    pub trailing_whitespace_lines: usize,
+    /// Whether the last line of the file has no line ending.
+    pub missing_final_newline: bool,
+}
+
+impl FileStyle {
+    /// Gets the style of the line endings.
+    pub fn line_endings(&self) -> LineEndingStyle {
+        match (self.lf_lines > 0, self.crlf_lines > 0, self.cr_lines > 0) {
+            (false, false, false) => LineEndingStyle::None,
+            (true, false, false) => LineEndingStyle::Lf,
+            (false, true, false) => LineEndingStyle::Crlf,
+            (false, false, true) => LineEndingStyle::Cr,
+            _ => LineEndingStyle::Mixed,
+        }
+    }
+
+    /// Gets the style of the indentation.
+    pub fn indentation(&self) -> IndentStyle {
+        match (
+            self.tab_indented_lines > 0,
+            self.space_indented_lines > 0,
+            self.mixed_indented_lines > 0,
+        ) {
+            (false, false, false) => IndentStyle::None,
+            (true, false, false) => IndentStyle::Tabs,
+            (false, true, false) => IndentStyle::Spaces,
+            _ => IndentStyle::Mixed,
+        }
+    }
+
+    /// Checks whether any line ends in something other than a line feed.
+    pub fn has_non_lf_line_endings(&self) -> bool {
+        !matches!(
+            self.line_endings(),
+            LineEndingStyle::None | LineEndingStyle::Lf
+        )
+    }
+
+    /// Checks whether the indentation departs from the indentation expected
+    /// in the file's language, either by mixing tabs and spaces or by using
+    /// only the other of the two.
+    pub fn has_unexpected_indentation(&self, expected: IndentStyle) -> bool {
+        match self.indentation() {
+            IndentStyle::None => false,
+            IndentStyle::Mixed => true,
+            indentation => indentation != expected,
+        }
+    }
+
+    /// Checks whether the file departs from LF line endings, the indentation
+    /// expected in its language, no trailing whitespace or a final newline.
+    pub fn has_issues(&self, expected: IndentStyle) -> bool {
+        self.has_non_lf_line_endings()
+            || self.has_unexpected_indentation(expected)
+            || self.trailing_whitespace_lines > 0
+            || self.missing_final_newline
+    }
+}
+
+/// Tallied styles of a group of text files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct StyleCounts {
+    /// The number of files with LF line endings.
+    pub lf_files: usize,
+    /// The number of files with CRLF line endings.
+    pub crlf_files: usize,
+    /// The number of files with lone CR line endings.
+    pub cr_files: usize,
+    /// The number of files with mixed line endings.
+    pub mixed_line_ending_files: usize,
+    /// The number of files indented with tabs.
+    pub tab_indented_files: usize,
+    /// The number of files indented with spaces.
+    pub space_indented_files: usize,
+    /// The number of files with mixed indentation.
+    pub mixed_indented_files: usize,
+    /// The number of files whose indentation is not the one expected in
+    /// their language.
+    pub unexpected_indentation_files: usize,
+    /// The number of files with trailing whitespace.
+    pub trailing_whitespace_files: usize,
+    /// The number of lines with trailing whitespace.
+    pub trailing_whitespace_lines: usize,
+    /// The number of files without a final newline.
+    pub missing_final_newline_files: usize,
+    /// The number of files with any of the issues checked by
+    /// `FileStyle::has_issues`.
+    pub files_with_issues: usize,
+}
+
+impl StyleCounts {
+    /// Adds the style of a single file, given the indentation expected in its
+    /// language.
+    pub fn add_file(&mut self, style: &FileStyle, expected: IndentStyle) {
+        match style.line_endings() {
+            LineEndingStyle::None => {}
+            LineEndingStyle::Lf => self.lf_files += 1,
+            LineEndingStyle::Crlf => self.crlf_files += 1,
+            LineEndingStyle::Cr => self.cr_files += 1,
+            LineEndingStyle::Mixed => self.mixed_line_ending_files += 1,
+        }
+
+        match style.indentation() {
+            IndentStyle::None => {}
+            IndentStyle::Tabs => self.tab_indented_files += 1,
+            IndentStyle::Spaces => self.space_indented_files += 1,
+            IndentStyle::Mixed => self.mixed_indented_files += 1,
+        }
+
+        self.trailing_whitespace_files += usize::from(style.trailing_whitespace_lines > 0);
+        self.trailing_whitespace_lines += style.trailing_whitespace_lines;
+        self.missing_final_newline_files += usize::from(style.missing_final_newline);
+        self.unexpected_indentation_files +=
+            usize::from(style.has_unexpected_indentation(expected));
+        self.files_with_issues += usize::from(style.has_issues(expected));
+    }
+
+    /// Adds the styles of another group of files.
+    pub fn add(&mut self, other: &StyleCounts) {
+        self.lf_files += other.lf_files;
+        self.crlf_files += other.crlf_files;
+        self.cr_files += other.cr_files;
+        self.mixed_line_ending_files += other.mixed_line_ending_files;
+        self.tab_indented_files += other.tab_indented_files;
+        self.space_indented_files += other.space_indented_files;
+        self.mixed_indented_files += other.mixed_indented_files;
+        self.unexpected_indentation_files += other.unexpected_indentation_files;
+        self.trailing_whitespace_files += other.trailing_whitespace_files;
+        self.trailing_whitespace_lines += other.trailing_whitespace_lines;
+        self.missing_final_newline_files += other.missing_final_newline_files;
+        self.files_with_issues += other.files_with_issues;
+    }
+
+    /// Removes the styles of another group of files.
+    pub fn remove(&mut self, other: &StyleCounts) {
+        self.lf_files = self.lf_files.saturating_sub(other.lf_files);
+        self.crlf_files = self.crlf_files.saturating_sub(other.crlf_files);
+        self.cr_files = self.cr_files.saturating_sub(other.cr_files);
+        self.mixed_line_ending_files = self
+            .mixed_line_ending_files
+            .saturating_sub(other.mixed_line_ending_files);
+        self.tab_indented_files = self
+            .tab_indented_files
+            .saturating_sub(other.tab_indented_files);
+        self.space_indented_files = self
+            .space_indented_files
+            .saturating_sub(other.space_indented_files);
+        self.mixed_indented_files = self
+            .mixed_indented_files
+            .saturating_sub(other.mixed_indented_files);
+        self.unexpected_indentation_files = self
+            .unexpected_indentation_files
+            .saturating_sub(other.unexpected_indentation_files);
+        self.trailing_whitespace_files = self
+            .trailing_whitespace_files
+            .saturating_sub(other.trailing_whitespace_files);
+        self.trailing_whitespace_lines = self
+            .trailing_whitespace_lines
+            .saturating_sub(other.trailing_whitespace_lines);
+        self.missing_final_newline_files = self
+            .missing_final_newline_files
+            .saturating_sub(other.missing_final_newline_files);
+        self.files_with_issues = self
+            .files_with_issues
+            .saturating_sub(other.files_with_issues);
+    }
+
+    /// Gets the number of files whose line endings are not LF.
+    pub fn non_lf_files(&self) -> usize {
+        self.crlf_files + self.cr_files + self.mixed_line_ending_files
+    }
+}
+
+/// Detects the style of a file fed to it in chunks of any size. Every line
+/// ending is recognized, whatever the line count mode of the scan.
+#[derive(Debug, Clone, Default)]
+pub struct StyleDetector {
+    /// The style detected so far.
+    style: FileStyle,
+    /// Whether the previous chunk ended in a carriage return, which is part
+    /// of a CRLF line ending if the next chunk starts with a line feed.
+    after_carriage_return: bool,
+    /// The number of bytes of the current line seen so far.
+    line_len: usize,
+    /// The last byte of the current line seen so far.
+    last_byte: Option<u8>,
+    /// Whether the current line has content after its indentation.
+    has_content: bool,
+    /// The number of tabs in the indentation of the current line.
+    indent_tabs: usize,
+    /// The number of spaces in the indentation of the current line.
+    indent_spaces: usize,
+    /// The indentation of the last line with content indented with spaces
+    /// only, or zero after an unindented line.
+    previous_indent: usize,
+    /// How often each step between the indentation of consecutive lines was
+    /// seen, by the size of the step.
+    indent_steps: [usize; MAX_INDENT_WIDTH + 1],
+}
+
+impl StyleDetector {
+    /// Creates a new style detector.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Detects the style of the lines in the next chunk of the file.
+    pub fn feed(&mut self, mut chunk: &[u8]) {
+        if chunk.is_empty() {
+            return;
+        }
+
+        if mem::take(&mut self.after_carriage_return) {
+            match chunk.strip_prefix(b"\n") {
+                Some(rest) => {
+                    self.style.crlf_lines += 1;
+                    chunk = rest;
+                }
+                None => self.style.cr_lines += 1,
+            }
+        }
+
+        let mut start = 0;
+
+        while let Some(offset) = memchr2(b'\n', b'\r', &chunk[start..]) {
+            let end = start + offset;
+            self.scan(&chunk[start..end]);
+            self.end_line();
+            start = end + 1;
+
+            if chunk[end] == b'\n' {
+                self.style.lf_lines += 1;
+                continue;
+            }
+
+            match chunk.get(start) {
+                Some(b'\n') => {
+                    self.style.crlf_lines += 1;
+                    start += 1;
+                }
+                Some(_) => self.style.cr_lines += 1,
+                None => self.after_carriage_return = true,
+            }
+        }
+
+        self.scan(&chunk[start..]);
+    }
+
+    /// Finishes detection at the end of the file and returns the style.
+    /// The final newline is only checked if the whole file was fed.
+    pub fn finish(mut self, whole_file: bool) -> FileStyle {
+        if self.after_carriage_return {
+            self.style.cr_lines += 1;
+        }
+
+        if self.line_len > 0 {
+            self.style.missing_final_newline = whole_file;
+            self.end_line();
+        }
+
+        self.style.indent_width = (1..=MAX_INDENT_WIDTH)
+            .filter(|&step| self.indent_steps[step] > 0)
+            .max_by_key(|&step| (self.indent_steps[step], std::cmp::Reverse(step)));
+        self.style
+    }
+
+    /// Scans part of the current line, without its line ending.
+    fn scan(&mut self, part: &[u8]) {
+        let Some(&last_byte) = part.last() else {
+            return;
+        };
+
+        if !self.has_content {
+            for &byte in part {
+                match byte {
+                    b' ' => self.indent_spaces += 1,
+                    b'\t' => self.indent_tabs += 1,
+                    _ => {
+                        self.has_content = true;
+                        break;
+                    }
+                }
+            }
+        }
+
+        self.line_len += part.len();
+        self.last_byte = Some(last_byte);
+    }
+
+    /// Records the style of the current line once it ends, and starts the
+    /// next.
+    fn end_line(&mut self) {
+        if matches!(self.last_byte, Some(b' ' | b'\t')) {
+            self.style.trailing_whitespace_lines += 1;
+        }
+
+        // Lines of only whitespace say nothing about indentation.
+        if self.has_content {
+            match (self.indent_tabs > 0, self.indent_spaces > 0) {
+                (false, false) => self.previous_indent = 0,
+                (true, false) => self.style.tab_indented_lines += 1,
+                (false, true) => {
+                    self.style.space_indented_lines += 1;
+
+                    if let Some(step) = self.indent_spaces.checked_sub(self.previous_indent) {
+                        if (1..=MAX_INDENT_WIDTH).contains(&step) {
+                            self.indent_steps[step] += 1;
+                        }
+                    }
+
+                    self.previous_indent = self.indent_spaces;
+                }
+                (true, true) => self.style.mixed_indented_lines += 1,
+            }
+        }
+
+        self.line_len = 0;
+        self.last_byte = None;
+        self.has_content = false;
+        self.indent_tabs = 0;
+        self.indent_spaces = 0;
+    }
+}
+
+/// Style detection tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Detects the style of the given source, fed in chunks of the given size.
+    fn detect(source: &str, chunk_size: usize) -> FileStyle {
+        let mut detector = StyleDetector::new();
+        source
+            .as_bytes()
+            .chunks(chunk_size)
+            .for_each(|chunk| detector.feed(chunk));
+        detector.finish(true)
+    }
+
+    /// Tests detection of line ending styles.
+    #[test]
+    fn test_line_endings() {
+        let style = |source| detect(source, 64).line_endings();
+        assert_eq!(style(""), LineEndingStyle::None);
+        assert_eq!(style("a"), LineEndingStyle::None);
+        assert_eq!(style("a\nb\n"), LineEndingStyle::Lf);
+        assert_eq!(style("a\r\nb\r\n"), LineEndingStyle::Crlf);
+        assert_eq!(style("a\rb\r"), LineEndingStyle::Cr);
+        assert_eq!(style("a\r\nb\n"), LineEndingStyle::Mixed);
+
+        assert!(!detect("", 64).missing_final_newline);
+        assert!(!detect("a\n", 64).missing_final_newline);
+        assert!(!detect("a\r", 64).missing_final_newline);
+        assert!(detect("a\nb", 64).missing_final_newline);
+    }
+
+    /// Tests detection of indentation styles and widths, and of trailing
+    /// whitespace.
+    #[test]
+    fn test_whitespace() {
+        let spaces = "fn a() {\n    if x {\n        y();\n    }\n\n}\n";
+        let style = detect(spaces, 64);
+        assert_eq!(style.indentation(), IndentStyle::Spaces);
+        assert_eq!(style.indent_width, Some(4));
+        assert_eq!(style.trailing_whitespace_lines, 0);
+        assert!(!style.has_issues(IndentStyle::Spaces));
+        assert!(style.has_unexpected_indentation(IndentStyle::Tabs));
+
+        let tabs = "a {\n\tb;\n\t\tc; \n  \n}";
+        let style = detect(tabs, 64);
+        assert_eq!(style.indentation(), IndentStyle::Tabs);
+        assert_eq!(style.indent_width, None);
+        assert_eq!(style.trailing_whitespace_lines, 2);
+        assert!(style.has_unexpected_indentation(IndentStyle::Spaces));
+        assert!(!style.has_unexpected_indentation(IndentStyle::Tabs));
+        assert!(style.missing_final_newline);
+        assert!(style.has_issues(IndentStyle::Tabs));
+        assert!(!detect("a {\n\tb;\n}\n", 64).has_issues(IndentStyle::Tabs));
+
+        let mixed = detect("a\n\t  b\n", 64);
+        assert_eq!(mixed.indentation(), IndentStyle::Mixed);
+        assert!(mixed.has_unexpected_indentation(IndentStyle::Tabs));
+        assert_eq!(
+            detect("a\n  b\n\tc\n", 64).indentation(),
+            IndentStyle::Mixed
+        );
+    }
+
+    /// Tests that the style is detected the same however the file is split
+    /// into chunks.
+    #[test]
+    fn test_chunks() {
+        let source = "a {\r\n  b;  \r\n    c;\r\r\n\tz\n  }";
+        let whole = detect(source, source.len());
+        assert_eq!(whole.lf_lines, 1);
+        assert_eq!(whole.crlf_lines, 3);
+        assert_eq!(whole.cr_lines, 1);
+        assert_eq!(whole.trailing_whitespace_lines, 1);
+        assert_eq!(whole.indent_width, Some(2));
+        assert!(whole.missing_final_newline);
+
+        for chunk_size in 1..source.len() {
+            assert_eq!(detect(source, chunk_size), whole);
+        }
+    }
+}
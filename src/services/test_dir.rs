@@ -0,0 +1,51 @@
+//! Temporary directories for tests.
+
+use std::env;
+use std::fs;
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of test directories created by the process, which keeps the
+/// names of directories created at the same time unique.
+static DIR_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// An empty temporary directory for a test. The directory and everything in
+/// it are removed when it is dropped, even if the test fails.
+#[derive(Debug)]
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    /// Creates a temporary directory whose name starts with the prefix.
+    pub fn new(prefix: &str) -> Self {
+        let path = env::temp_dir().join(format!(
+            "code-stats-{prefix}-{}-{}",
+            process::id(),
+            DIR_COUNT.fetch_add(1, Ordering::Relaxed)
+        ));
+        let _ = fs::remove_dir_all(&path);
+        fs::create_dir_all(&path).unwrap();
+        Self(path)
+    }
+}
+
+impl Deref for TestDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl AsRef<Path> for TestDir {
+    fn as_ref(&self) -> &Path {
+        &self.0
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.0);
+    }
+}
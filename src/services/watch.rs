@@ -0,0 +1,464 @@
+//! Live updates of collected statistics as files change.
+
+use crate::services::*;
+use ignore::overrides::Override;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::collections::{BTreeSet, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
+use tokio::task::spawn_blocking;
+use tokio::time::{timeout_at, Instant};
+
+/// How long to wait for further changes before handling a batch.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(250);
+
+/// The longest a batch of changes is held back while files keep changing.
+const WATCH_MAX_DELAY: Duration = Duration::from_secs(2);
+
+/// The names of the ignore files read by the walker, in addition to those
+/// configured in the scan options.
+const IGNORE_FILE_NAMES: &[&str] = &[".gitignore", ".ignore"];
+
+/// A change to the collected statistics of a project.
+#[derive(Debug, Clone, PartialEq)]
+pub enum StatsPatch {
+    /// A file or directory was removed, given its full path.
+    Remove(PathBuf),
+    /// A directory was added, given its full path.
+    Dir(PathBuf),
+    /// A file was added or changed, given its full path and statistics.
+    File(PathBuf, FileStats),
+    /// A path could not be scanned.
+    Error(ScanError),
+}
+
+/// A batch of paths that changed.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct WatchChanges {
+    /// The paths that were created, modified or removed.
+    pub paths: BTreeSet<PathBuf>,
+    /// Whether events may have been missed, so that a full rescan is needed.
+    pub rescan: bool,
+}
+
+impl WatchChanges {
+    /// Adds the paths of an event to the batch.
+    fn add(&mut self, event: notify::Result<Event>) {
+        match event {
+            Ok(event) => {
+                self.rescan |= event.need_rescan();
+
+                if !matches!(event.kind, EventKind::Access(_)) {
+                    self.paths.extend(event.paths);
+                }
+            }
+            Err(_) => self.rescan = true,
+        }
+    }
+}
+
+/// Watches a directory tree for changes.
+pub struct StatsWatcher {
+    /// The underlying watcher, which stops watching when dropped.
+    _watcher: RecommendedWatcher,
+    /// The events reported by the watcher.
+    events: UnboundedReceiver<notify::Result<Event>>,
+}
+
+impl StatsWatcher {
+    /// Starts watching a directory tree.
+    pub fn new(root: &Path) -> notify::Result<Self> {
+        let (sender, events) = unbounded_channel();
+        let mut watcher = notify::recommended_watcher(move |event| {
+            let _ = sender.send(event);
+        })?;
+        watcher.watch(root, RecursiveMode::Recursive)?;
+
+        Ok(Self {
+            _watcher: watcher,
+            events,
+        })
+    }
+
+    /// Waits for files to change, gathering changes that arrive in quick
+    /// succession into one batch. Returns `None` if the watcher stopped.
+    pub async fn changes(&mut self) -> Option<WatchChanges> {
+        let mut changes = WatchChanges::default();
+        changes.add(self.events.recv().await?);
+        let deadline = Instant::now() + WATCH_MAX_DELAY;
+
+        while let Ok(Some(event)) = timeout_at(
+            (Instant::now() + WATCH_DEBOUNCE).min(deadline),
+            self.events.recv(),
+        )
+        .await
+        {
+            changes.add(event);
+        }
+
+        Some(changes)
+    }
+}
+
+/// Turns changed paths into patches to previously collected statistics,
+/// applying the same scan options, path filters and project configuration as
+/// the original scan.
+pub struct StatsUpdater {
+    /// The root of the project.
+    root: PathBuf,
+    /// The canonical path of the root, if it differs from the root, since
+    /// watchers may report changes beneath a symlinked root by their
+    /// canonical paths.
+    canonical_root: Option<PathBuf>,
+    /// The options the project was scanned with.
+    options: ScanOptions,
+    /// The state used to walk changed paths, which is only locked by the
+    /// blocking task walking them.
+    walk: Arc<Mutex<UpdateWalk>>,
+}
+
+impl StatsUpdater {
+    /// Creates an updater for a project scanned with the given options and
+    /// path filters.
+    pub fn new(root: &Path, options: &ScanOptions, filters: &PathFilters) -> ServiceResult<Self> {
+        let overrides = filters
+            .overrides(root)
+            .map_err(|err| ServiceError::Config {
+                path: root.to_path_buf(),
+                message: err.to_string(),
+            })?;
+        let configs = ProjectConfigs::new(root, LanguageRegistry::global());
+        let walk = UpdateWalk {
+            root: root.to_path_buf(),
+            options: options.clone(),
+            overrides,
+            registry: configs.registry(),
+            configs,
+            attributes: GitAttributes::new(root),
+            loaded_dirs: HashSet::new(),
+        };
+
+        Ok(Self {
+            root: root.to_path_buf(),
+            canonical_root: fs::canonicalize(root).ok().filter(|path| path != root),
+            options: options.clone(),
+            walk: Arc::new(Mutex::new(walk)),
+        })
+    }
+
+    /// Gets the patches for a batch of changed paths, or `None` if a file that
+    /// affects the whole scan changed and the project must be rescanned. The
+    /// changed paths are read on a blocking thread.
+    pub async fn patches(
+        &mut self,
+        stats: &CodeStats,
+        paths: &BTreeSet<PathBuf>,
+    ) -> Option<Vec<StatsPatch>> {
+        let mut changed = Vec::new();
+
+        for path in paths.iter().filter_map(|path| self.project_path(path)) {
+            if path == self.root {
+                continue;
+            }
+
+            if self.is_scan_config_file(&path) {
+                return None;
+            }
+
+            // Changes within git's own directory, and within directories that
+            // were not scanned, do not affect the statistics.
+            let parent_scanned = path.parent().is_some_and(|dir| stats.contains_dir(dir));
+
+            if path.starts_with(self.root.join(".git")) || !parent_scanned {
+                continue;
+            }
+
+            let known_dir = stats.contains_dir(&path);
+            changed.push((path, known_dir));
+        }
+
+        let walk = self.walk.clone();
+        let patches = spawn_blocking(move || {
+            let mut walk = walk.lock().unwrap();
+            let mut patches = Vec::new();
+
+            for (path, known_dir) in changed {
+                walk.changed_path(&path, known_dir, &mut patches);
+            }
+
+            patches
+        })
+        .await
+        .unwrap();
+
+        Some(patches)
+    }
+
+    /// Gets the path of a changed path beneath the root, as the project was
+    /// scanned, or `None` if it is outside the project.
+    fn project_path(&self, path: &Path) -> Option<PathBuf> {
+        if path.starts_with(&self.root) {
+            return Some(path.to_path_buf());
+        }
+
+        let relative_path = path.strip_prefix(self.canonical_root.as_ref()?).ok()?;
+        Some(self.root.join(relative_path))
+    }
+
+    /// Checks whether a path is an ignore, attributes or configuration file,
+    /// whose changes affect every file beneath it.
+    fn is_scan_config_file(&self, path: &Path) -> bool {
+        let is_config_name = path.file_name().is_some_and(|name| {
+            IGNORE_FILE_NAMES
+                .iter()
+                .copied()
+                .chain([GIT_ATTRIBUTES_FILE_NAME, PROJECT_CONFIG_FILE_NAME])
+                .chain(self.options.ignore_file_names.iter().map(String::as_str))
+                .any(|config_name| name == config_name)
+        });
+
+        is_config_name || path.starts_with(self.root.join(".git").join("info"))
+    }
+}
+
+/// The state used to walk the paths that changed within a project.
+struct UpdateWalk {
+    /// The root of the project.
+    root: PathBuf,
+    /// The options the project was scanned with.
+    options: ScanOptions,
+    /// The walker overrides built from the path filters.
+    overrides: Override,
+    /// The language registry used to detect languages.
+    registry: Arc<LanguageRegistry>,
+    /// The project configuration files loaded so far.
+    configs: ProjectConfigs,
+    /// The attributes files loaded so far.
+    attributes: GitAttributes,
+    /// The directories whose configuration and attributes files are loaded.
+    loaded_dirs: HashSet<PathBuf>,
+}
+
+impl UpdateWalk {
+    /// Adds the patches for a path that changed, given whether it is a
+    /// directory already in the statistics.
+    fn changed_path(&mut self, path: &Path, known_dir: bool, patches: &mut Vec<StatsPatch>) {
+        match fs::metadata(path) {
+            Ok(metadata) if metadata.is_dir() => {
+                // Changes within known directories are reported for the
+                // paths within them.
+                if !known_dir {
+                    self.walk(path, patches);
+                }
+            }
+            Ok(_) => {
+                patches.push(StatsPatch::Remove(path.to_path_buf()));
+                self.walk(path, patches);
+            }
+            Err(_) => patches.push(StatsPatch::Remove(path.to_path_buf())),
+        }
+    }
+
+    /// Loads the configuration and attributes files of a directory and all of
+    /// its ancestors within the project.
+    fn load_dirs(&mut self, dir: &Path) {
+        let mut dirs = dir
+            .ancestors()
+            .take_while(|ancestor| ancestor.starts_with(&self.root))
+            .collect::<Vec<_>>();
+        dirs.reverse();
+
+        for dir in dirs {
+            if self.loaded_dirs.insert(dir.to_path_buf()) {
+                self.configs.load_dir(dir);
+                self.attributes.load_dir(dir);
+            }
+        }
+    }
+
+    /// Walks a path that changed, from its parent directory so that the
+    /// ignore files above it apply, adding patches for what is found.
+    fn walk(&mut self, start: &Path, patches: &mut Vec<StatsPatch>) {
+        let Some(parent) = start.parent() else {
+            return;
+        };
+        let parent_depth = parent
+            .strip_prefix(&self.root)
+            .map(|relative_path| relative_path.components().count())
+            .unwrap_or_default();
+        let mut walk_builder = self.options.walk_builder(parent);
+        walk_builder.overrides(self.overrides.clone());
+
+        if let Some(max_depth) = self.options.max_depth {
+            if max_depth <= parent_depth {
+                return;
+            }
+
+            walk_builder.max_depth(Some(max_depth - parent_depth));
+        }
+
+        let filter_start = start.to_path_buf();
+        walk_builder.filter_entry(move |entry| {
+            entry.depth() == 0 || entry.path().starts_with(&filter_start)
+        });
+        self.load_dirs(parent);
+
+        for entry in walk_builder.build() {
+            let entry = match entry {
+                Ok(entry) if entry.depth() == 0 => continue,
+                Ok(entry) => entry,
+                Err(err) => {
+                    patches.push(StatsPatch::Error(ScanError::from_walk(&self.root, &err)));
+                    continue;
+                }
+            };
+            // As in a scan, symbolic links that are not followed are skipped.
+            let metadata = entry.metadata();
+            let entry_path = entry.into_path();
+            let metadata = match metadata {
+                Ok(metadata) => metadata,
+                Err(err) => {
+                    if !self.configs.is_excluded(&entry_path, false) {
+                        patches.push(StatsPatch::Error(ScanError::from_walk(&self.root, &err)));
+                    }
+
+                    continue;
+                }
+            };
+
+            if metadata.is_file() {
+                if self.configs.is_excluded(&entry_path, false) {
+                    continue;
+                }
+
+                let tags = self.attributes.tags(&entry_path);
+                let language = self.configs.language_override(&entry_path);
+                let component = self.configs.component(&entry_path);
+
+                match file_stats(&self.registry, &entry_path, language, &self.options) {
+                    Ok(mut stats) => {
+                        stats.tags = tags.apply(stats.tags);
+                        stats.component = component;
+                        patches.push(StatsPatch::File(entry_path, stats));
+                    }
+                    Err(err) => {
+                        patches.push(StatsPatch::Error(ScanError::from_io(&entry_path, &err)));
+                    }
+                }
+            } else if metadata.is_dir() {
+                if self.configs.is_excluded(&entry_path, true) {
+                    continue;
+                }
+
+                self.load_dirs(&entry_path);
+                patches.push(StatsPatch::Dir(entry_path));
+            }
+        }
+    }
+}
+
+/// Watch tests.
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Scans a directory from scratch.
+    async fn scan(root: &Path) -> CodeStats {
+        let options = ScanOptions {
+            cache: false,
+            ..ScanOptions::default()
+        };
+
+        collect_stats(
+            root,
+            &options,
+            &PathFilters::default(),
+            &CancellationToken::new(),
+            &progress_channel().0,
+        )
+        .await
+        .unwrap()
+    }
+
+    /// Tests that patching statistics after changes gives the same result as
+    /// a fresh scan.
+    #[tokio::test]
+    async fn test_stats_updater() {
+        let root = TestDir::new("watch");
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("src/main.rs"), "fn main() {}\n").unwrap();
+        fs::write(root.join("src/lib.rs"), "pub fn lib() {}\n").unwrap();
+        fs::write(root.join("docs/notes.md"), "# Notes\n").unwrap();
+
+        let mut stats = scan(&root).await;
+        let mut updater =
+            StatsUpdater::new(&root, &ScanOptions::default(), &PathFilters::default()).unwrap();
+
+        fs::write(root.join("src/main.rs"), "fn main() {\n    lib();\n}\n").unwrap();
+        fs::remove_file(root.join("src/lib.rs")).unwrap();
+        fs::remove_dir_all(root.join("docs")).unwrap();
+        fs::create_dir_all(root.join("web/app")).unwrap();
+        fs::write(root.join("web/app/index.ts"), "export {};\n").unwrap();
+
+        let paths = ["src/main.rs", "src/lib.rs", "docs", "web"]
+            .into_iter()
+            .map(|path| root.join(path))
+            .collect();
+        let patches = updater.patches(&stats, &paths).await.unwrap();
+        patches
+            .into_iter()
+            .for_each(|patch| stats.apply_patch(patch));
+
+        assert_eq!(stats.stats, scan(&root).await.stats);
+
+        let paths = [root.join(".gitignore")].into_iter().collect();
+        assert!(updater.patches(&stats, &paths).await.is_none());
+    }
+
+    /// Tests that changes reported by the canonical paths of a symlinked root
+    /// are applied, and that patches outside the root are dropped.
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_symlinked_root() {
+        let dir = TestDir::new("watch-link");
+        fs::create_dir_all(dir.join("real/src")).unwrap();
+        fs::write(dir.join("real/src/main.rs"), "fn main() {}\n").unwrap();
+        std::os::unix::fs::symlink(dir.join("real"), dir.join("link")).unwrap();
+        let root = dir.join("link");
+        let canonical_root = fs::canonicalize(dir.join("real")).unwrap();
+
+        let mut stats = scan(&root).await;
+        let mut updater =
+            StatsUpdater::new(&root, &ScanOptions::default(), &PathFilters::default()).unwrap();
+
+        fs::write(canonical_root.join("src/lib.rs"), "pub fn lib() {}\n").unwrap();
+        let paths = [canonical_root.join("src/lib.rs")].into_iter().collect();
+        let patches = updater.patches(&stats, &paths).await.unwrap();
+        assert!(!patches.is_empty());
+        patches
+            .into_iter()
+            .for_each(|patch| stats.apply_patch(patch));
+
+        assert_eq!(stats.stats, scan(&root).await.stats);
+
+        let outside = dir.join("outside.rs");
+        let before = stats.stats.clone();
+        stats.apply_patch(StatsPatch::Dir(dir.join("other")));
+        stats.apply_patch(StatsPatch::File(
+            outside.clone(),
+            file_stats(
+                &LanguageRegistry::global(),
+                dir.join("real/src/main.rs"),
+                None,
+                &ScanOptions::default(),
+            )
+            .unwrap(),
+        ));
+        stats.apply_patch(StatsPatch::Remove(outside));
+        assert_eq!(stats.stats, before);
+    }
+}